@@ -35,6 +35,10 @@ impl<T> HashmapArena<T> {
         HashmapArena::default()
     }
 
+    pub fn reserve(&mut self, additional: usize) {
+        self.buckets[0].reserve(additional);
+    }
+
     pub fn alloc(&mut self, value: T) -> ArenaItemId<T> {
         self.buckets[0].alloc(value)
     }
@@ -43,6 +47,14 @@ impl<T> HashmapArena<T> {
         self.buckets.iter_mut().find(|v| v.owns(id))?.dealloc(id)
     }
 
+    pub fn restore(&mut self, id: ArenaItemId<T>, value: T) {
+        self.buckets
+            .iter_mut()
+            .find(|v| v.owns(id))
+            .expect("Unknown bucket")
+            .restore(id, value)
+    }
+
     #[inline]
     pub fn get(&self, id: ArenaItemId<T>) -> Option<&T> {
         self.buckets.iter().find(|v| v.owns(id))?.get(id)
@@ -53,6 +65,14 @@ impl<T> HashmapArena<T> {
         self.buckets.iter_mut().find(|v| v.owns(id))?.get_mut(id)
     }
 
+    pub fn iter<'a>(&'a self) -> impl Iterator<Item = (ArenaItemId<T>, &'a T)> + 'a {
+        self.buckets.iter().flat_map(|bucket| bucket.iter())
+    }
+
+    pub fn iter_mut<'a>(&'a mut self) -> impl Iterator<Item = (ArenaItemId<T>, &'a mut T)> + 'a {
+        self.buckets.iter_mut().flat_map(|bucket| bucket.iter_mut())
+    }
+
     #[inline]
     pub unsafe fn get_as_mut<'a>(&mut self, id: ArenaItemId<T>) -> Option<&'a mut T> {
         as_mut(self.get_mut(id))
@@ -65,6 +85,16 @@ impl<T> HashmapArena<T> {
         let second = unsafe { self.get_as_mut(second_id) };
         (first, second)
     }
+
+    pub fn get_mut_many(&mut self, ids: &[ArenaItemId<T>]) -> Vec<(ArenaItemId<T>, &mut T)> {
+        self.buckets
+            .iter_mut()
+            .flat_map(|bucket| {
+                let own_ids: Vec<_> = ids.iter().cloned().filter(|&id| bucket.owns(id)).collect();
+                bucket.get_mut_many(&own_ids)
+            })
+            .collect()
+    }
 }
 
 impl<T> AddAssign<Self> for HashmapArena<T> {
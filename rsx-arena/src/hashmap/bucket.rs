@@ -46,6 +46,10 @@ impl<T> HashmapBucket<T> {
         self.bucket_id == id.bucket_id
     }
 
+    pub fn reserve(&mut self, additional: usize) {
+        self.map.reserve(additional);
+    }
+
     pub fn alloc(&mut self, value: T) -> ArenaItemId<T> {
         let item_id = ArenaItemId {
             bucket_id: self.bucket_id,
@@ -60,6 +64,13 @@ impl<T> HashmapBucket<T> {
         self.map.remove(&id)
     }
 
+    // Ids are never reused, so a deallocated entry can be put back under its old id.
+    pub fn restore(&mut self, id: ArenaItemId<T>, value: T) {
+        debug_assert_eq!(self.bucket_id, id.bucket_id);
+        let old_value = self.map.insert(id, value);
+        debug_assert!(old_value.is_none());
+    }
+
     #[inline]
     pub fn get(&self, id: ArenaItemId<T>) -> Option<&T> {
         debug_assert_eq!(self.bucket_id, id.bucket_id);
@@ -72,6 +83,14 @@ impl<T> HashmapBucket<T> {
         self.map.get_mut(&id)
     }
 
+    pub fn iter<'a>(&'a self) -> impl Iterator<Item = (ArenaItemId<T>, &'a T)> + 'a {
+        self.map.iter().map(|(&id, value)| (id, value))
+    }
+
+    pub fn iter_mut<'a>(&'a mut self) -> impl Iterator<Item = (ArenaItemId<T>, &'a mut T)> + 'a {
+        self.map.iter_mut().map(|(&id, value)| (id, value))
+    }
+
     #[inline]
     pub unsafe fn get_as_mut<'a>(&mut self, id: ArenaItemId<T>) -> Option<&'a mut T> {
         as_mut(self.get_mut(id))
@@ -84,4 +103,14 @@ impl<T> HashmapBucket<T> {
         let second = unsafe { self.get_as_mut(second_id) };
         (first, second)
     }
+
+    // Ids are deduplicated first, so no entry is handed out twice.
+    pub fn get_mut_many(&mut self, ids: &[ArenaItemId<T>]) -> Vec<(ArenaItemId<T>, &mut T)> {
+        let mut ids = ids.to_vec();
+        ids.sort_by_key(|id| id.entry_id.to_index());
+        ids.dedup();
+        ids.into_iter()
+            .filter_map(|id| Some((id, unsafe { self.get_as_mut(id)? })))
+            .collect()
+    }
 }
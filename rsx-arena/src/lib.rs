@@ -9,6 +9,8 @@ CONDITIONS OF ANY KIND, either express or implied. See the License for the
 specific language governing permissions and limitations under the License.
 */
 
+#![feature(conservative_impl_trait)]
+
 extern crate fnv;
 extern crate num_traits;
 extern crate smallvec;
@@ -16,10 +18,12 @@ extern crate smallvec;
 mod util;
 mod common;
 mod hashmap;
+mod persistent;
 mod vec;
 
 pub mod types {
     pub use common::*;
     pub use hashmap::*;
+    pub use persistent::*;
     pub use vec::*;
 }
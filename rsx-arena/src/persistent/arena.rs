@@ -0,0 +1,119 @@
+/*
+Copyright 2016 Mozilla
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+this file except in compliance with the License. You may obtain a copy of the
+License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed
+under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+CONDITIONS OF ANY KIND, either express or implied. See the License for the
+specific language governing permissions and limitations under the License.
+*/
+
+use std::ops::AddAssign;
+
+use smallvec::SmallVec;
+
+use persistent::bucket::PersistentBucket;
+use types::ArenaItemId;
+use util::as_mut;
+
+// Arenas are extended by taking over the other's buckets, so ids from either one
+// stay valid, the same way the hashmap-based arena does it.
+#[derive(Debug, PartialEq)]
+pub struct PersistentArena<T> {
+    buckets: SmallVec<[PersistentBucket<T>; 1]>
+}
+
+impl<T> Default for PersistentArena<T> {
+    fn default() -> Self {
+        PersistentArena {
+            buckets: SmallVec::from_buf([PersistentBucket::new()])
+        }
+    }
+}
+
+impl<T> PersistentArena<T> {
+    pub fn new() -> Self {
+        PersistentArena::default()
+    }
+
+    pub fn owns(&self, id: ArenaItemId<T>) -> bool {
+        self.buckets.iter().any(|v| v.owns(id))
+    }
+
+    pub fn snapshot(&self) -> Self
+    where
+        T: Clone
+    {
+        PersistentArena {
+            buckets: self.buckets.iter().map(|v| v.snapshot()).collect()
+        }
+    }
+
+    pub fn reserve(&mut self, additional: usize) {
+        self.buckets[0].reserve(additional);
+    }
+
+    pub fn alloc(&mut self, value: T) -> ArenaItemId<T> {
+        self.buckets[0].alloc(value)
+    }
+
+    pub fn dealloc(&mut self, id: ArenaItemId<T>) -> Option<T> {
+        self.buckets.iter_mut().find(|v| v.owns(id))?.dealloc(id)
+    }
+
+    pub fn restore(&mut self, id: ArenaItemId<T>, value: T) {
+        self.buckets
+            .iter_mut()
+            .find(|v| v.owns(id))
+            .expect("Unknown bucket")
+            .restore(id, value)
+    }
+
+    #[inline]
+    pub fn get(&self, id: ArenaItemId<T>) -> Option<&T> {
+        self.buckets.iter().find(|v| v.owns(id))?.get(id)
+    }
+
+    #[inline]
+    pub fn get_mut(&mut self, id: ArenaItemId<T>) -> Option<&mut T> {
+        self.buckets.iter_mut().find(|v| v.owns(id))?.get_mut(id)
+    }
+
+    pub fn iter<'a>(&'a self) -> impl Iterator<Item = (ArenaItemId<T>, &'a T)> + 'a {
+        self.buckets.iter().flat_map(|bucket| bucket.iter())
+    }
+
+    pub fn iter_mut<'a>(&'a mut self) -> impl Iterator<Item = (ArenaItemId<T>, &'a mut T)> + 'a {
+        self.buckets.iter_mut().flat_map(|bucket| bucket.iter_mut())
+    }
+
+    #[inline]
+    pub unsafe fn get_as_mut<'a>(&mut self, id: ArenaItemId<T>) -> Option<&'a mut T> {
+        as_mut(self.get_mut(id))
+    }
+
+    #[inline]
+    pub fn get_mut_pair(&mut self, first_id: ArenaItemId<T>, second_id: ArenaItemId<T>) -> (Option<&mut T>, Option<&mut T>) {
+        assert_ne!(first_id, second_id);
+        let first = unsafe { self.get_as_mut(first_id) };
+        let second = unsafe { self.get_as_mut(second_id) };
+        (first, second)
+    }
+
+    pub fn get_mut_many(&mut self, ids: &[ArenaItemId<T>]) -> Vec<(ArenaItemId<T>, &mut T)> {
+        self.buckets
+            .iter_mut()
+            .flat_map(|bucket| {
+                let own_ids: Vec<_> = ids.iter().cloned().filter(|&id| bucket.owns(id)).collect();
+                bucket.get_mut_many(&own_ids)
+            })
+            .collect()
+    }
+}
+
+impl<T> AddAssign<Self> for PersistentArena<T> {
+    fn add_assign(&mut self, rhs: Self) {
+        self.buckets.extend(rhs.buckets)
+    }
+}
@@ -0,0 +1,237 @@
+/*
+Copyright 2016 Mozilla
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+this file except in compliance with the License. You may obtain a copy of the
+License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed
+under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+CONDITIONS OF ANY KIND, either express or implied. See the License for the
+specific language governing permissions and limitations under the License.
+*/
+
+use std::cell::Cell;
+use std::fmt;
+use std::rc::Rc;
+
+use types::{ArenaItemId, BucketId, EntryId};
+use util::as_mut;
+
+const CHUNK_SIZE: usize = 32;
+const PAGE_SIZE: usize = 32;
+
+type Chunk<T> = Vec<Option<Rc<T>>>;
+type Page<T> = Vec<Rc<Chunk<T>>>;
+
+// Entries are shared between a bucket and its snapshots, and are only copied once
+// written to. Nothing is shared before the first snapshot, which is what provides
+// the function used for copying, so values don't need to be `Clone` otherwise.
+// Chunks are grouped in pages, so a write only copies the path down to its entry.
+pub struct PersistentBucket<T> {
+    bucket_id: BucketId<T>,
+    len: usize,
+    pages: Rc<Vec<Rc<Page<T>>>>,
+    clone_fn: Cell<Option<fn(&T) -> T>>
+}
+
+impl<T> fmt::Debug for PersistentBucket<T>
+where
+    T: fmt::Debug
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.debug_struct("PersistentBucket")
+            .field("bucket_id", &self.bucket_id)
+            .field("pages", &self.pages)
+            .finish()
+    }
+}
+
+// Snapshots share their bucket id, so values are compared as well, unless nothing
+// has been copied since.
+impl<T> PartialEq for PersistentBucket<T>
+where
+    T: PartialEq
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.bucket_id == other.bucket_id
+            && self.len == other.len
+            && (Rc::ptr_eq(&self.pages, &other.pages) || self.iter().eq(other.iter()))
+    }
+}
+
+impl<T> Default for PersistentBucket<T> {
+    fn default() -> Self {
+        PersistentBucket {
+            bucket_id: BucketId::generate(),
+            len: 0,
+            pages: Rc::new(vec![]),
+            clone_fn: Cell::new(None)
+        }
+    }
+}
+
+impl<T> PersistentBucket<T> {
+    pub fn new() -> Self {
+        PersistentBucket::default()
+    }
+
+    pub fn owns(&self, id: ArenaItemId<T>) -> bool {
+        self.bucket_id == id.bucket_id
+    }
+
+    pub fn snapshot(&self) -> Self
+    where
+        T: Clone
+    {
+        self.clone_fn.set(Some(T::clone));
+        PersistentBucket {
+            bucket_id: self.bucket_id,
+            len: self.len,
+            pages: Rc::clone(&self.pages),
+            clone_fn: Cell::new(Some(T::clone))
+        }
+    }
+
+    pub fn reserve(&mut self, additional: usize) {
+        let pages = Rc::make_mut(&mut self.pages);
+        let needed = (self.len + additional + CHUNK_SIZE * PAGE_SIZE - 1) / (CHUNK_SIZE * PAGE_SIZE);
+        let additional_pages = needed.saturating_sub(pages.len());
+        pages.reserve(additional_pages);
+    }
+
+    pub fn alloc(&mut self, value: T) -> ArenaItemId<T> {
+        let item_id = ArenaItemId {
+            bucket_id: self.bucket_id,
+            entry_id: EntryId::new(self.len as u32)
+        };
+        let pages = Rc::make_mut(&mut self.pages);
+        if self.len % (CHUNK_SIZE * PAGE_SIZE) == 0 {
+            pages.push(Rc::new(Vec::with_capacity(PAGE_SIZE)));
+        }
+        let page = Rc::make_mut(pages.last_mut().expect("Missing page"));
+        if self.len % CHUNK_SIZE == 0 {
+            page.push(Rc::new(Vec::with_capacity(CHUNK_SIZE)));
+        }
+        let chunk = page.last_mut().expect("Missing chunk");
+        Rc::make_mut(chunk).push(Some(Rc::new(value)));
+        self.len += 1;
+        item_id
+    }
+
+    pub fn dealloc(&mut self, id: ArenaItemId<T>) -> Option<T> {
+        let clone_fn = self.clone_fn.get();
+        let value = self.entry_mut(id)?.take()?;
+        match Rc::try_unwrap(value) {
+            Ok(value) => Some(value),
+            Err(value) => Some(clone_fn.expect("Entry shared without a snapshot")(&value))
+        }
+    }
+
+    // Ids are never reused, so a deallocated entry can be put back in its old place.
+    pub fn restore(&mut self, id: ArenaItemId<T>, value: T) {
+        let entry = self.entry_mut(id).expect("Unknown entry");
+        debug_assert!(entry.is_none());
+        *entry = Some(Rc::new(value));
+    }
+
+    #[inline]
+    pub fn get(&self, id: ArenaItemId<T>) -> Option<&T> {
+        debug_assert_eq!(self.bucket_id, id.bucket_id);
+        let (page_index, chunk_index, index) = position(id);
+        let value = self.pages.get(page_index)?.get(chunk_index)?.get(index)?;
+        value.as_ref().map(|v| &**v)
+    }
+
+    #[inline]
+    pub fn get_mut(&mut self, id: ArenaItemId<T>) -> Option<&mut T> {
+        let clone_fn = self.clone_fn.get();
+        let value = self.entry_mut(id)?.as_mut()?;
+        if Rc::get_mut(value).is_none() {
+            *value = Rc::new(clone_fn.expect("Entry shared without a snapshot")(value));
+        }
+        Rc::get_mut(value)
+    }
+
+    pub fn iter<'a>(&'a self) -> impl Iterator<Item = (ArenaItemId<T>, &'a T)> + 'a {
+        let bucket_id = self.bucket_id;
+        self.pages
+            .iter()
+            .flat_map(|page| page.iter())
+            .enumerate()
+            .flat_map(move |(chunk_index, chunk)| {
+                chunk.iter().enumerate().filter_map(move |(index, value)| {
+                    let item_id = ArenaItemId {
+                        bucket_id,
+                        entry_id: EntryId::new((chunk_index * CHUNK_SIZE + index) as u32)
+                    };
+                    value.as_ref().map(|value| (item_id, &**value))
+                })
+            })
+    }
+
+    // Every entry is made unique before being handed out, copying the shared ones.
+    pub fn iter_mut<'a>(&'a mut self) -> impl Iterator<Item = (ArenaItemId<T>, &'a mut T)> + 'a {
+        let bucket_id = self.bucket_id;
+        let clone_fn = self.clone_fn.get();
+        let pages = Rc::make_mut(&mut self.pages).iter_mut();
+        let chunks = pages
+            .flat_map(|page| Rc::make_mut(page).iter_mut())
+            .enumerate();
+        chunks.flat_map(move |(chunk_index, chunk)| {
+            let entries = Rc::make_mut(chunk).iter_mut().enumerate();
+            entries.filter_map(move |(index, value)| {
+                let value = value.as_mut()?;
+                if Rc::get_mut(value).is_none() {
+                    *value = Rc::new(clone_fn.expect("Entry shared without a snapshot")(value));
+                }
+                let item_id = ArenaItemId {
+                    bucket_id,
+                    entry_id: EntryId::new((chunk_index * CHUNK_SIZE + index) as u32)
+                };
+                Rc::get_mut(value).map(|value| (item_id, value))
+            })
+        })
+    }
+
+    #[inline]
+    pub unsafe fn get_as_mut<'a>(&mut self, id: ArenaItemId<T>) -> Option<&'a mut T> {
+        as_mut(self.get_mut(id))
+    }
+
+    // Copying a page or chunk never moves the values themselves, so the first
+    // reference stays valid while the second one is being made unique.
+    #[inline]
+    pub fn get_mut_pair(&mut self, first_id: ArenaItemId<T>, second_id: ArenaItemId<T>) -> (Option<&mut T>, Option<&mut T>) {
+        assert_ne!(first_id, second_id);
+        let first = unsafe { self.get_as_mut(first_id) };
+        let second = unsafe { self.get_as_mut(second_id) };
+        (first, second)
+    }
+
+    // Same as above, and ids are deduplicated first, so no entry is handed out twice.
+    pub fn get_mut_many(&mut self, ids: &[ArenaItemId<T>]) -> Vec<(ArenaItemId<T>, &mut T)> {
+        let mut ids = ids.to_vec();
+        ids.sort_by_key(|id| id.entry_id.to_index());
+        ids.dedup();
+        ids.into_iter()
+            .filter_map(|id| Some((id, unsafe { self.get_as_mut(id)? })))
+            .collect()
+    }
+
+    fn entry_mut(&mut self, id: ArenaItemId<T>) -> Option<&mut Option<Rc<T>>> {
+        debug_assert_eq!(self.bucket_id, id.bucket_id);
+        let (page_index, chunk_index, index) = position(id);
+        let page = Rc::make_mut(&mut self.pages).get_mut(page_index)?;
+        let chunk = Rc::make_mut(page).get_mut(chunk_index)?;
+        Rc::make_mut(chunk).get_mut(index)
+    }
+}
+
+fn position<T>(id: ArenaItemId<T>) -> (usize, usize, usize) {
+    let index = id.entry_id.to_index();
+    let chunk_index = index / CHUNK_SIZE;
+    (
+        chunk_index / PAGE_SIZE,
+        chunk_index % PAGE_SIZE,
+        index % CHUNK_SIZE
+    )
+}
@@ -17,7 +17,7 @@ use util::as_mut;
 #[derive(Debug)]
 pub struct VecArena<T> {
     bucket_id: BucketId<T>,
-    vec: Vec<T>
+    vec: Vec<Option<T>>
 }
 
 impl<T> PartialEq for VecArena<T> {
@@ -44,29 +44,70 @@ impl<T> VecArena<T> {
         self.bucket_id == id.bucket_id
     }
 
+    pub fn reserve(&mut self, additional: usize) {
+        self.vec.reserve(additional);
+    }
+
     pub fn alloc(&mut self, value: T) -> ArenaItemId<T> {
         let item_id = ArenaItemId {
             bucket_id: self.bucket_id,
             entry_id: EntryId::new(self.vec.len() as u32)
         };
-        self.vec.push(value);
+        self.vec.push(Some(value));
         item_id
     }
 
-    pub fn dealloc(&mut self, _: ArenaItemId<T>) -> Option<T> {
-        unimplemented!()
+    pub fn dealloc(&mut self, id: ArenaItemId<T>) -> Option<T> {
+        debug_assert_eq!(self.bucket_id, id.bucket_id);
+        self.vec.get_mut(id.entry_id.to_index())?.take()
+    }
+
+    // Ids are never reused, so a deallocated entry can be put back in its old place.
+    pub fn restore(&mut self, id: ArenaItemId<T>, value: T) {
+        debug_assert_eq!(self.bucket_id, id.bucket_id);
+        let entry = &mut self.vec[id.entry_id.to_index()];
+        debug_assert!(entry.is_none());
+        *entry = Some(value);
     }
 
     #[inline]
     pub fn get(&self, id: ArenaItemId<T>) -> Option<&T> {
         debug_assert_eq!(self.bucket_id, id.bucket_id);
-        Some(unsafe { self.vec.get_unchecked(id.entry_id.to_index()) })
+        unsafe { self.vec.get_unchecked(id.entry_id.to_index()) }.as_ref()
     }
 
     #[inline]
     pub fn get_mut(&mut self, id: ArenaItemId<T>) -> Option<&mut T> {
         debug_assert_eq!(self.bucket_id, id.bucket_id);
-        Some(unsafe { self.vec.get_unchecked_mut(id.entry_id.to_index()) })
+        unsafe { self.vec.get_unchecked_mut(id.entry_id.to_index()) }.as_mut()
+    }
+
+    pub fn iter<'a>(&'a self) -> impl Iterator<Item = (ArenaItemId<T>, &'a T)> + 'a {
+        let bucket_id = self.bucket_id;
+        self.vec
+            .iter()
+            .enumerate()
+            .filter_map(move |(index, value)| {
+                let item_id = ArenaItemId {
+                    bucket_id,
+                    entry_id: EntryId::new(index as u32)
+                };
+                value.as_ref().map(|value| (item_id, value))
+            })
+    }
+
+    pub fn iter_mut<'a>(&'a mut self) -> impl Iterator<Item = (ArenaItemId<T>, &'a mut T)> + 'a {
+        let bucket_id = self.bucket_id;
+        self.vec
+            .iter_mut()
+            .enumerate()
+            .filter_map(move |(index, value)| {
+                let item_id = ArenaItemId {
+                    bucket_id,
+                    entry_id: EntryId::new(index as u32)
+                };
+                value.as_mut().map(|value| (item_id, value))
+            })
     }
 
     #[inline]
@@ -81,6 +122,31 @@ impl<T> VecArena<T> {
         let second = unsafe { self.get_as_mut(second_id) };
         (first, second)
     }
+
+    // Entries are split off the front of the vec one after the other, in id order,
+    // so each of them is only borrowed once.
+    pub fn get_mut_many(&mut self, ids: &[ArenaItemId<T>]) -> Vec<(ArenaItemId<T>, &mut T)> {
+        let mut ids = ids.to_vec();
+        ids.sort_by_key(|id| id.entry_id.to_index());
+        ids.dedup();
+
+        let mut values = Vec::with_capacity(ids.len());
+        let mut rest = &mut self.vec[..];
+        let mut offset = 0;
+        for id in ids {
+            let index = id.entry_id.to_index();
+            let (entry, tail) = match { rest }.split_at_mut(index - offset).1.split_first_mut() {
+                Some(split) => split,
+                None => break
+            };
+            rest = tail;
+            offset = index + 1;
+            if let Some(value) = entry.as_mut() {
+                values.push((id, value));
+            }
+        }
+        values
+    }
 }
 
 impl<T> AddAssign<Self> for VecArena<T> {
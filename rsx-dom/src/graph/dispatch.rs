@@ -0,0 +1,146 @@
+/*
+Copyright 2016 Mozilla
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+this file except in compliance with the License. You may obtain a copy of the
+License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed
+under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+CONDITIONS OF ANY KIND, either express or implied. See the License for the
+specific language governing permissions and limitations under the License.
+*/
+
+use rsx_shared::traits::TGenericEvent;
+
+use types::{
+    Closure,
+    DOMAttribute,
+    DOMAttributeName,
+    DOMAttributeValue,
+    DOMNode,
+    DOMNodeId,
+    DOMTree,
+    EventType,
+    ListenerHandle,
+    ListenerOptions
+};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum EventPhase {
+    Capture,
+    Target,
+    Bubble
+}
+
+// Listeners only ever get to see the event itself, so any flags they set have to live
+// on it. Each listener is handed its own clone, which should share those flags.
+pub trait TDispatchEvent: TGenericEvent + Clone {
+    fn is_propagation_stopped(&self) -> bool;
+    fn is_default_prevented(&self) -> bool;
+    fn set_default_prevented(&self, prevented: bool);
+}
+
+impl TDispatchEvent for () {
+    fn is_propagation_stopped(&self) -> bool {
+        false
+    }
+
+    fn is_default_prevented(&self) -> bool {
+        false
+    }
+
+    fn set_default_prevented(&self, _: bool) {}
+}
+
+#[derive(Debug, PartialEq)]
+pub struct DispatchResult<E, S, C, L> {
+    pub invoked: Vec<(DOMNodeId<E, S, C, L>, EventPhase)>,
+    pub propagation_stopped: bool,
+    pub default_prevented: bool
+}
+
+impl<E, S, C, L> DOMTree<E, S, C, L>
+where
+    E: TDispatchEvent
+{
+    // Listeners stored as attributes don't capture, so they only run in the target and
+    // bubble phases, like inline handlers do on the web. Added listeners run after them.
+    pub fn dispatch_event(&mut self, target_id: DOMNodeId<E, S, C, L>, ty: EventType, event: E) -> DispatchResult<E, S, C, L> {
+        let mut path: Vec<_> = self
+            .get(target_id)
+            .ancestors_refs()
+            .map(|node| node.id())
+            .collect();
+        path.reverse();
+
+        let mut result = DispatchResult {
+            invoked: vec![],
+            propagation_stopped: false,
+            default_prevented: event.is_default_prevented()
+        };
+        let mut expired = vec![];
+
+        let phases = path
+            .iter()
+            .map(|&id| (id, EventPhase::Capture))
+            .chain(Some((target_id, EventPhase::Target)))
+            .chain(path.iter().rev().map(|&id| (id, EventPhase::Bubble)));
+
+        for (id, phase) in phases {
+            for (callback, options, handle) in listeners(self.get(id).into_value(), &ty, phase) {
+                callback.invoke(event.clone());
+                result.invoked.push((id, phase));
+                // Passive listeners can still flip the shared flag, so it's put back after
+                // each of them, and only set by the other listeners.
+                if options.passive {
+                    event.set_default_prevented(result.default_prevented);
+                } else if event.is_default_prevented() {
+                    result.default_prevented = true;
+                }
+                if let (true, Some(handle)) = (options.once, handle) {
+                    expired.push((id, handle));
+                }
+            }
+            // Listeners on the same node still run after propagation is stopped.
+            if event.is_propagation_stopped() {
+                result.propagation_stopped = true;
+                break;
+            }
+        }
+
+        for (id, handle) in expired {
+            self.get_mut(id).remove_event_listener(handle);
+        }
+
+        result
+    }
+}
+
+fn listeners<'a, E, S, C, L>(
+    node: &'a DOMNode<E, S, C, L>,
+    ty: &'a EventType,
+    phase: EventPhase
+) -> impl Iterator<Item = (&'a Closure<E>, ListenerOptions, Option<ListenerHandle>)> {
+    let attributes: &[_] = match phase {
+        EventPhase::Capture => &[],
+        EventPhase::Target | EventPhase::Bubble => node.data.attributes_slice()
+    };
+    let attributes = attributes
+        .iter()
+        .filter_map(move |attribute| match attribute {
+            &DOMAttribute(DOMAttributeName::EventType(ref name), DOMAttributeValue::EventListener(ref l)) if name == ty => {
+                Some((l, ListenerOptions::default(), None))
+            }
+            _ => None
+        });
+    let added = node
+        .listeners
+        .iter()
+        .filter(move |listener| &listener.ty == ty)
+        .filter(move |listener| match phase {
+            EventPhase::Capture => listener.options.capture,
+            EventPhase::Target => true,
+            EventPhase::Bubble => !listener.options.capture
+        })
+        .map(|listener| (&listener.callback, listener.options, Some(listener.handle)));
+    attributes.chain(added)
+}
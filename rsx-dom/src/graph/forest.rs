@@ -0,0 +1,113 @@
+/*
+Copyright 2016 Mozilla
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+this file except in compliance with the License. You may obtain a copy of the
+License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed
+under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+CONDITIONS OF ANY KIND, either express or implied. See the License for the
+specific language governing permissions and limitations under the License.
+*/
+
+use rsx_shared::traits::{TComputedStyles, TGenericEvent, TLayoutNode, TStyleDeclarations};
+use rsx_tree::types::Forest;
+
+use types::{DOMArenaRef, DOMArenaRefMut, DOMNode, DOMNodeId, DOMTree};
+
+// A fragment without the synthetic root a `DOMTree` keeps above its document. Layout
+// is only built once the roots are put back into a tree.
+#[derive(Debug, PartialEq)]
+pub struct DOMForest<E, S, C, L> {
+    raw: Forest<DOMNode<E, S, C, L>>
+}
+
+impl<E, S, C, L> Default for DOMForest<E, S, C, L> {
+    fn default() -> Self {
+        DOMForest { raw: Forest::new() }
+    }
+}
+
+impl<E, S, C, L> DOMForest<E, S, C, L> {
+    pub fn new() -> Self {
+        DOMForest::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.raw.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.raw.is_empty()
+    }
+
+    pub fn get(&self, id: DOMNodeId<E, S, C, L>) -> DOMArenaRef<E, S, C, L> {
+        DOMArenaRef::from(self.raw.get(id))
+    }
+
+    pub fn get_mut(&mut self, id: DOMNodeId<E, S, C, L>) -> DOMArenaRefMut<E, S, C, L> {
+        DOMArenaRefMut::from(self.raw.get_mut(id))
+    }
+
+    pub fn is_root(&self, id: DOMNodeId<E, S, C, L>) -> bool {
+        self.raw.is_root(id)
+    }
+
+    pub fn roots_ids_iter<'a>(&'a self) -> impl Iterator<Item = DOMNodeId<E, S, C, L>> + 'a {
+        self.raw.roots_ids_iter()
+    }
+
+    pub fn roots_refs_iter<'a>(&'a self) -> impl Iterator<Item = DOMArenaRef<'a, E, S, C, L>> + 'a {
+        self.raw.roots_refs_iter().map(DOMArenaRef::from)
+    }
+
+    pub fn push_node(&mut self, node: DOMNode<E, S, C, L>) -> DOMNodeId<E, S, C, L> {
+        self.raw.push_root(node)
+    }
+
+    pub fn insert_node(&mut self, index: usize, node: DOMNode<E, S, C, L>) -> DOMNodeId<E, S, C, L> {
+        self.raw.insert_root(index, node)
+    }
+
+    // Every top level node of the tree becomes a root of its own.
+    pub fn push_tree(&mut self, tree: DOMTree<E, S, C, L>) -> Vec<DOMNodeId<E, S, C, L>> {
+        let (_, forest) = tree.into_inner().into_forest();
+        forest
+            .into_trees()
+            .into_iter()
+            .map(|tree| self.raw.push_tree(tree))
+            .collect()
+    }
+
+    // The removed root becomes the document of a tree of its own.
+    pub fn remove_root(&mut self, id: DOMNodeId<E, S, C, L>) -> Option<DOMTree<E, S, C, L>>
+    where
+        E: TGenericEvent,
+        S: TStyleDeclarations,
+        C: TComputedStyles<Styles = S>,
+        L: TLayoutNode<Styles = S>
+    {
+        let tree = self.raw.remove_root(id)?;
+        Some(DOMTree::from_inner(
+            Forest::from(tree).into_tree(DOMNode::default())
+        ))
+    }
+
+    pub fn into_tree(self) -> DOMTree<E, S, C, L>
+    where
+        E: TGenericEvent,
+        S: TStyleDeclarations,
+        C: TComputedStyles<Styles = S>,
+        L: TLayoutNode<Styles = S>
+    {
+        DOMTree::from_inner(self.raw.into_tree(DOMNode::default()))
+    }
+}
+
+impl<E, S, C, L> DOMTree<E, S, C, L> {
+    // The synthetic root is dropped, and the document with any of its siblings become
+    // the roots of the forest.
+    pub fn into_forest(self) -> DOMForest<E, S, C, L> {
+        let (_, raw) = self.into_inner().into_forest();
+        DOMForest { raw }
+    }
+}
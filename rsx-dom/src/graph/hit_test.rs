@@ -0,0 +1,55 @@
+/*
+Copyright 2016 Mozilla
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+this file except in compliance with the License. You may obtain a copy of the
+License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed
+under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+CONDITIONS OF ANY KIND, either express or implied. See the License for the
+specific language governing permissions and limitations under the License.
+*/
+
+use rsx_shared::traits::{TComputedStyles, TDOMNode, TGenericEvent, TLayoutNode, TStyleDeclarations};
+
+use types::{DOMNodeId, DOMTree};
+
+// The layout and styles traits leave rects and visibility opaque, so hit testing
+// needs these to be able to look inside them.
+pub trait THitTestRect {
+    fn contains_point(&self, x: f32, y: f32) -> bool;
+}
+
+pub trait THitTestVisibility {
+    fn is_hidden(&self) -> bool;
+}
+
+impl<E, S, C, L> DOMTree<E, S, C, L>
+where
+    E: TGenericEvent,
+    S: TStyleDeclarations,
+    C: TComputedStyles,
+    L: TLayoutNode,
+    C::Visibility: THitTestVisibility,
+    L::BoundingClientRect: THitTestRect
+{
+    pub fn element_from_point(&self, x: f32, y: f32) -> Option<DOMNodeId<E, S, C, L>> {
+        self.elements_from_point(x, y).into_iter().next()
+    }
+
+    // Nodes paint in tree order, so the reverse of a pre-order walk puts later siblings
+    // and descendants first. Hidden nodes are skipped, but not their children, since
+    // those can still be visible. The synthetic root is never a hit.
+    pub fn elements_from_point(&self, x: f32, y: f32) -> Vec<DOMNodeId<E, S, C, L>> {
+        let mut hits: Vec<_> = self
+            .root()
+            .descendants_refs()
+            .skip(1)
+            .filter(|node| node.is_normal() || node.is_shadow_host())
+            .filter(|node| !node.computed_styles().visibility().is_hidden())
+            .filter(|node| node.get_global_bounding_client_rect().contains_point(x, y))
+            .map(|node| node.id())
+            .collect();
+        hits.reverse();
+        hits
+    }
+}
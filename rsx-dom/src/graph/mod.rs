@@ -9,14 +9,24 @@ CONDITIONS OF ANY KIND, either express or implied. See the License for the
 specific language governing permissions and limitations under the License.
 */
 
+mod dispatch;
+mod forest;
+mod hit_test;
 mod node_id;
 mod node_ref;
 mod node_ref_mut;
 mod node_ref_mut_pair;
+mod observer;
 mod tree;
+mod walk;
 
+pub use self::dispatch::*;
+pub use self::forest::*;
+pub use self::hit_test::*;
 pub use self::node_id::*;
 pub use self::node_ref::*;
 pub use self::node_ref_mut::*;
 pub use self::node_ref_mut_pair::*;
+pub use self::observer::*;
 pub use self::tree::*;
+pub use self::walk::*;
@@ -9,17 +9,69 @@ CONDITIONS OF ANY KIND, either express or implied. See the License for the
 specific language governing permissions and limitations under the License.
 */
 
+use std::hash::Hash;
 use std::ops::Deref;
 
-use rsx_tree::types::Ref;
+use rsx_shared::traits::{TComputedStyles, TGenericEvent, TLayoutNode, TStyleDeclarations};
+use rsx_tree::types::{Edge, NodePath, Ref};
 
-use types::{DOMNode, DOMNodeEdgeIds, DOMNodeId, DOMNodeSiblingIds};
+use types::{DOMNode, DOMNodeEdgeIds, DOMNodeId, DOMNodeSiblingIds, DOMTree};
 
 #[derive(Debug, PartialEq)]
 pub struct DOMArenaRef<'a, E: 'a, S: 'a, C: 'a, L: 'a> {
     raw: Ref<'a, DOMNode<E, S, C, L>>
 }
 
+impl<'a, E, S, C, L> Copy for DOMArenaRef<'a, E, S, C, L> {}
+
+impl<'a, E, S, C, L> Clone for DOMArenaRef<'a, E, S, C, L> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+#[derive(Debug)]
+pub enum DOMEdge<'a, E: 'a, S: 'a, C: 'a, L: 'a> {
+    Open(DOMArenaRef<'a, E, S, C, L>),
+    Close(DOMArenaRef<'a, E, S, C, L>)
+}
+
+impl<'a, E, S, C, L> Eq for DOMEdge<'a, E, S, C, L> {}
+
+impl<'a, E, S, C, L> PartialEq for DOMEdge<'a, E, S, C, L> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (&DOMEdge::Open(a), &DOMEdge::Open(b)) | (&DOMEdge::Close(a), &DOMEdge::Close(b)) => a.id() == b.id(),
+            _ => false
+        }
+    }
+}
+
+impl<'a, E, S, C, L> Copy for DOMEdge<'a, E, S, C, L> {}
+
+impl<'a, E, S, C, L> Clone for DOMEdge<'a, E, S, C, L> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, E, S, C, L> DOMEdge<'a, E, S, C, L> {
+    pub fn node(self) -> DOMArenaRef<'a, E, S, C, L> {
+        match self {
+            DOMEdge::Open(v) | DOMEdge::Close(v) => v
+        }
+    }
+}
+
+impl<'a, E, S, C, L> From<Edge<'a, DOMNode<E, S, C, L>>> for DOMEdge<'a, E, S, C, L> {
+    fn from(edge: Edge<'a, DOMNode<E, S, C, L>>) -> Self {
+        match edge {
+            Edge::Open(raw) => DOMEdge::Open(DOMArenaRef::from(raw)),
+            Edge::Close(raw) => DOMEdge::Close(DOMArenaRef::from(raw))
+        }
+    }
+}
+
 impl<'a, E, S, C, L> From<Ref<'a, DOMNode<E, S, C, L>>> for DOMArenaRef<'a, E, S, C, L> {
     fn from(raw: Ref<'a, DOMNode<E, S, C, L>>) -> Self {
         DOMArenaRef { raw }
@@ -43,6 +95,10 @@ impl<'a, E, S, C, L> DOMArenaRef<'a, E, S, C, L> {
         self.raw.try_into_value().expect("Node deallocated")
     }
 
+    pub(crate) fn inner(&self) -> Ref<'a, DOMNode<E, S, C, L>> {
+        self.raw
+    }
+
     pub fn get(&self, id: DOMNodeId<E, S, C, L>) -> DOMArenaRef<'a, E, S, C, L> {
         DOMArenaRef::from(self.raw.tree().get(id))
     }
@@ -99,6 +155,14 @@ impl<'a, E, S, C, L> DOMArenaRef<'a, E, S, C, L> {
         self.raw.edge_ids()
     }
 
+    pub fn child_index(&self) -> Option<usize> {
+        self.raw.child_index()
+    }
+
+    pub fn path(&self) -> Option<NodePath> {
+        self.raw.path()
+    }
+
     pub fn children_iter(&self) -> impl Iterator<Item = &'a DOMNode<E, S, C, L>> {
         self.raw.children_values_iter()
     }
@@ -110,4 +174,43 @@ impl<'a, E, S, C, L> DOMArenaRef<'a, E, S, C, L> {
     pub fn traverse_iter(&self) -> impl Iterator<Item = &'a DOMNode<E, S, C, L>> {
         self.raw.traverse_values_iter()
     }
+
+    pub fn ancestors_refs(&self) -> impl Iterator<Item = DOMArenaRef<'a, E, S, C, L>> {
+        self.raw.ancestors_refs_iter().map(DOMArenaRef::from)
+    }
+
+    pub fn children_refs(&self) -> impl Iterator<Item = DOMArenaRef<'a, E, S, C, L>> {
+        self.raw.children_refs_iter().map(DOMArenaRef::from)
+    }
+
+    pub fn descendants_refs(&self) -> impl Iterator<Item = DOMArenaRef<'a, E, S, C, L>> {
+        self.raw.descendants_refs_iter().map(DOMArenaRef::from)
+    }
+
+    pub fn traverse_edges(&self) -> impl Iterator<Item = DOMEdge<'a, E, S, C, L>> {
+        self.raw.traverse_edges_iter().map(DOMEdge::from)
+    }
+}
+
+impl<'a, E, S, C, L> DOMArenaRef<'a, E, S, C, L>
+where
+    E: TGenericEvent,
+    S: TStyleDeclarations + Clone,
+    C: TComputedStyles<Styles = S>,
+    L: TLayoutNode<Styles = S>
+{
+    pub fn clone_node(&self, deep: bool) -> DOMTree<E, S, C, L> {
+        let mut tree = DOMTree::default();
+        tree.root_mut().append_clone_of(self, deep);
+        tree
+    }
+}
+
+impl<'a, E, S, C, L> DOMArenaRef<'a, E, S, C, L>
+where
+    S: Hash
+{
+    pub fn subtree_hash(&self) -> u64 {
+        self.raw.subtree_hash()
+    }
 }
@@ -9,12 +9,27 @@ CONDITIONS OF ANY KIND, either express or implied. See the License for the
 specific language governing permissions and limitations under the License.
 */
 
+use std::cmp::Ordering;
 use std::ops::{Deref, DerefMut};
 
-use rsx_shared::traits::{TComputedStyles, TDOMNode, TGenericEvent, TLayoutNode, TStyleDeclarations};
-use rsx_tree::types::{Ref, RefMut};
+use fnv::FnvHashMap;
 
-use types::{DOMArenaRef, DOMArenaRefMutPair, DOMNode, DOMNodeEdgeIds, DOMNodeId, DOMNodeIdPair, DOMNodeSiblingIds, DOMTree};
+use rsx_shared::traits::{TComputedStyles, TDOMNode, TGenericEvent, TLayoutNode, TStyleDeclarations};
+pub use rsx_tree::types::FilterAction;
+use rsx_tree::types::{NodePath, Ref, RefMut};
+
+use types::{
+    DOMArenaRef,
+    DOMArenaRefMutPair,
+    DOMNode,
+    DOMNodeEdgeIds,
+    DOMNodeId,
+    DOMNodeIdPair,
+    DOMNodeSiblingIds,
+    DOMTree,
+    DOMWalkRefMut,
+    Walk
+};
 
 #[derive(Debug, PartialEq)]
 pub struct DOMArenaRefMut<'a, E: 'a, S: 'a, C: 'a, L: 'a> {
@@ -52,8 +67,15 @@ impl<'a, E, S, C, L> DOMArenaRefMut<'a, E, S, C, L> {
         self.raw.try_value().expect("Node deallocated")
     }
 
+    // Nodes are mostly touched for layout bookkeeping, which isn't a DOM mutation, so
+    // observers are only told through the methods that change the DOM itself.
     pub(crate) fn value_mut(&mut self) -> &mut DOMNode<E, S, C, L> {
-        self.raw.try_value_mut().expect("Node deallocated")
+        let id = self.raw.id();
+        self.raw
+            .tree_mut()
+            .get_mut(id)
+            .try_into_value()
+            .expect("Node deallocated")
     }
 
     pub(crate) fn into_value(self) -> &'a mut DOMNode<E, S, C, L> {
@@ -129,6 +151,14 @@ impl<'a, E, S, C, L> DOMArenaRefMut<'a, E, S, C, L> {
         self.raw.edge_ids()
     }
 
+    pub fn child_index(&self) -> Option<usize> {
+        self.raw.child_index()
+    }
+
+    pub fn path(&self) -> Option<NodePath> {
+        self.raw.tree().get(self.id()).path()
+    }
+
     pub fn append_tree(&mut self, other: DOMTree<E, S, C, L>) -> bool {
         self.raw.append_tree(other.into_inner())
     }
@@ -145,9 +175,102 @@ impl<'a, E, S, C, L> DOMArenaRefMut<'a, E, S, C, L> {
         DOMArenaRefMut::from(self.raw.prepend(node))
     }
 
+    pub fn insert_before(&mut self, node: DOMNode<E, S, C, L>) -> DOMArenaRefMut<E, S, C, L> {
+        DOMArenaRefMut::from(self.raw.insert_before(node))
+    }
+
+    pub fn insert_after(&mut self, node: DOMNode<E, S, C, L>) -> DOMArenaRefMut<E, S, C, L> {
+        DOMArenaRefMut::from(self.raw.insert_after(node))
+    }
+
     pub fn detach(&mut self) {
         self.raw.detach();
     }
+
+    pub fn replace_with(&mut self, node: DOMNode<E, S, C, L>) -> DOMNode<E, S, C, L> {
+        self.raw.replace_with(node)
+    }
+
+    pub fn replace_with_subtree(&mut self, id: DOMNodeId<E, S, C, L>) -> Option<()> {
+        self.raw.replace_with_subtree(id)
+    }
+
+    pub fn move_to(&mut self, new_parent_id: DOMNodeId<E, S, C, L>, position: usize) -> Option<()> {
+        self.raw.move_to(new_parent_id, position)
+    }
+
+    pub fn sort_children_by<F>(&mut self, compare: F) -> Option<()>
+    where
+        F: FnMut(&DOMNode<E, S, C, L>, &DOMNode<E, S, C, L>) -> Ordering
+    {
+        self.raw.sort_children_by(compare)
+    }
+
+    pub fn sort_children_by_key<K, F>(&mut self, f: F) -> Option<()>
+    where
+        K: Ord,
+        F: FnMut(&DOMNode<E, S, C, L>) -> K
+    {
+        self.raw.sort_children_by_key(f)
+    }
+
+    pub fn reverse_children(&mut self) -> Option<()> {
+        self.raw.reverse_children()
+    }
+
+    pub fn reorder_children(&mut self, ids: &[DOMNodeId<E, S, C, L>]) -> Option<()> {
+        self.raw.reorder_children(ids)
+    }
+
+    pub fn unwrap_node(&mut self) -> Option<DOMNode<E, S, C, L>> {
+        self.raw.unwrap_node()
+    }
+
+    pub fn wrap(&mut self, node: DOMNode<E, S, C, L>) -> DOMArenaRefMut<E, S, C, L> {
+        DOMArenaRefMut::from(self.raw.wrap(node))
+    }
+
+    pub fn walk_mut<F, G>(&mut self, mut enter: F, mut exit: G) -> bool
+    where
+        F: FnMut(DOMWalkRefMut<E, S, C, L>) -> Walk,
+        G: FnMut(DOMWalkRefMut<E, S, C, L>) -> Walk
+    {
+        self.raw.walk_mut(
+            |v| enter(DOMWalkRefMut::from(v)),
+            |v| exit(DOMWalkRefMut::from(v))
+        )
+    }
+
+    pub fn children_range(&self, first_id: DOMNodeId<E, S, C, L>, last_id: DOMNodeId<E, S, C, L>) -> Option<Vec<DOMNodeId<E, S, C, L>>> {
+        self.raw.children_range(first_id, last_id)
+    }
+
+    pub fn detach_range(&mut self, first_id: DOMNodeId<E, S, C, L>, last_id: DOMNodeId<E, S, C, L>) -> Option<Vec<DOMNodeId<E, S, C, L>>> {
+        self.raw.detach_range(first_id, last_id)
+    }
+
+    pub fn move_range_after(
+        &mut self,
+        first_id: DOMNodeId<E, S, C, L>,
+        last_id: DOMNodeId<E, S, C, L>,
+        anchor_id: DOMNodeId<E, S, C, L>
+    ) -> Option<()> {
+        self.raw.move_range_after(first_id, last_id, anchor_id)
+    }
+
+    pub fn retain_descendants<F>(&mut self, mut f: F) -> usize
+    where
+        F: FnMut(DOMArenaRef<E, S, C, L>) -> bool
+    {
+        self.raw.retain_descendants(|v| f(DOMArenaRef::from(v)))
+    }
+
+    pub fn filter_descendants<F>(&mut self, mut f: F) -> usize
+    where
+        F: FnMut(DOMArenaRef<E, S, C, L>) -> FilterAction
+    {
+        self.raw.filter_descendants(|v| f(DOMArenaRef::from(v)))
+    }
 }
 
 impl<'a, E, S, C, L> DOMArenaRefMut<'a, E, S, C, L>
@@ -157,50 +280,521 @@ where
     C: TComputedStyles,
     L: TLayoutNode
 {
+    pub fn append_clone_of(&mut self, other: &DOMArenaRef<E, S, C, L>, deep: bool) -> DOMNodeId<E, S, C, L>
+    where
+        S: TStyleDeclarations + Clone,
+        C: TComputedStyles<Styles = S>,
+        L: TLayoutNode<Styles = S>
+    {
+        if deep {
+            self.raw
+                .append_clone_of_with(&other.inner(), DOMNode::rebuild)
+                .expect("Node deallocated")
+        } else {
+            self.append(other.rebuild()).id()
+        }
+    }
+
+    pub fn set_text_content(&mut self, text: String, resources: &L::Resources)
+    where
+        L: TLayoutNode<TextMeasureMetadata = C>
+    {
+        self.raw
+            .update(|node| {
+                node.set_text_content(text, resources);
+                Some(())
+            })
+            .expect("Node deallocated")
+    }
+
+    pub fn reset_styles(&mut self) -> Option<()>
+    where
+        C: TComputedStyles<Styles = S>,
+        L: TLayoutNode<Styles = S>
+    {
+        self.raw.update(DOMNode::reset_styles)
+    }
+
+    pub fn apply_styles(&mut self, styles: S) -> Option<()>
+    where
+        C: TComputedStyles<Styles = S>,
+        L: TLayoutNode<Styles = S>
+    {
+        self.raw.update(|node| node.apply_styles(styles))
+    }
+
     pub fn append_with_layout(&mut self, child_id: DOMNodeId<E, S, C, L>, resources: &L::Resources) -> Result<(), ()>
     where
         L: TLayoutNode<TextMeasureMetadata = C, ImageMeasureMetadata = (), NormalMeasureMetadata = !>
     {
         debug_assert_eq!(self.get(child_id).parent_id(), None);
 
+        let parent_ids = [Some(self.id())];
+        self.record_layout_hook(&parent_ids, true);
+
         self.raw.append_id(child_id);
 
-        let (this_node, child_node) = self.get_mut_self_and(child_id).into_values();
-        child_node.apply_measurement_metadata_to_layout(resources, &this_node.computed_styles);
-        child_node.append_to_layout_node(this_node);
+        {
+            let (this_node, child_node) = self.get_mut_self_and(child_id).into_values();
+            child_node.apply_measurement_metadata_to_layout(resources, &this_node.computed_styles);
+            child_node.append_to_layout_node(this_node);
+        }
 
+        self.record_layout_hook(&parent_ids, false);
         Ok(())
     }
 
     pub fn remove_with_layout(&mut self, child_id: DOMNodeId<E, S, C, L>) -> Result<(), ()> {
         debug_assert_eq!(self.get(child_id).parent_id(), Some(self.id()));
 
+        let parent_ids = [Some(self.id())];
+        self.record_layout_hook(&parent_ids, true);
+
         self.raw.tree_mut().get_mut(child_id).detach();
 
-        let (this_node, child_node) = self.get_mut_self_and(child_id).into_values();
-        child_node.remove_from_layout_node(this_node);
+        {
+            let (this_node, child_node) = self.get_mut_self_and(child_id).into_values();
+            child_node.remove_from_layout_node(this_node);
+        }
 
+        self.record_layout_hook(&parent_ids, false);
         Ok(())
     }
 
-    pub fn build_layout(&mut self, resources: &L::Resources)
+    pub fn unwrap_node_with_layout(&mut self, resources: &L::Resources) -> Result<DOMNode<E, S, C, L>, ()>
+    where
+        L: TLayoutNode<TextMeasureMetadata = C, ImageMeasureMetadata = (), NormalMeasureMetadata = !>
+    {
+        let id = self.id();
+        let parent_id = self.parent_id().ok_or(())?;
+        let index = self.layout_index().ok_or(())?;
+        let child_ids: Vec<_> = self.raw.tree().get(id).children_ids_iter().collect();
+
+        self.record_layout_hook(&[Some(parent_id), Some(id)], true);
+
+        for &child_id in &child_ids {
+            let (this_node, child_node) = self.get_mut_self_and(child_id).into_values();
+            child_node.remove_from_layout_node(this_node);
+        }
+        {
+            let (parent_node, this_node) = self.get_mut_pair((parent_id, id)).into_values();
+            this_node.remove_from_layout_node(parent_node);
+        }
+
+        let node = self.raw.unwrap_node().ok_or(())?;
+
+        for (offset, &child_id) in child_ids.iter().enumerate() {
+            let (parent_node, child_node) = self.get_mut_pair((parent_id, child_id)).into_values();
+            child_node.apply_measurement_metadata_to_layout(resources, &parent_node.computed_styles);
+            child_node.insert_into_layout_node(parent_node, index + offset);
+        }
+
+        self.record_layout_hook(&[Some(parent_id)], false);
+        Ok(node)
+    }
+
+    pub fn wrap_with_layout(&mut self, node: DOMNode<E, S, C, L>, resources: &L::Resources) -> DOMArenaRefMut<E, S, C, L>
     where
         L: TLayoutNode<TextMeasureMetadata = C, ImageMeasureMetadata = (), NormalMeasureMetadata = !>
     {
-        debug_assert_eq!(self.layout_node().is_tainted(), false);
+        let id = self.id();
+        let parent_id = self.parent_id();
+        let index = self.layout_index();
+
+        self.record_layout_hook(&[parent_id], true);
+
+        if let Some(parent_id) = parent_id {
+            let (parent_node, this_node) = self.get_mut_pair((parent_id, id)).into_values();
+            this_node.remove_from_layout_node(parent_node);
+        }
+
+        let wrapper_id = self.raw.wrap(node).id();
+
+        {
+            let (wrapper_node, this_node) = self.get_mut_pair((wrapper_id, id)).into_values();
+            this_node.apply_measurement_metadata_to_layout(resources, &wrapper_node.computed_styles);
+            this_node.append_to_layout_node(wrapper_node);
+        }
+
+        if let (Some(parent_id), Some(index)) = (parent_id, index) {
+            let (parent_node, wrapper_node) = self.get_mut_pair((parent_id, wrapper_id)).into_values();
+            wrapper_node.apply_measurement_metadata_to_layout(resources, &parent_node.computed_styles);
+            wrapper_node.insert_into_layout_node(parent_node, index);
+        }
+
+        self.record_layout_hook(&[parent_id, Some(wrapper_id)], false);
+        self.get_mut(wrapper_id)
+    }
 
-        let mut next_child_id = self.first_child_id();
-        while let Some(child_id) = next_child_id {
-            {
-                let mut child_ref = self.get_mut(child_id);
-                child_ref.build_layout(resources);
+    pub fn replace_with_layout(&mut self, node: DOMNode<E, S, C, L>, resources: &L::Resources) -> DOMNode<E, S, C, L>
+    where
+        L: TLayoutNode<TextMeasureMetadata = C, ImageMeasureMetadata = (), NormalMeasureMetadata = !>
+    {
+        let child_ids: Vec<_> = self.raw.tree().get(self.id()).children_ids_iter().collect();
+        let parent_id = self.parent_id();
+
+        let parent_ids = [parent_id, Some(self.id())];
+        self.record_layout_hook(&parent_ids, true);
+
+        for &child_id in &child_ids {
+            let (this_node, child_node) = self.get_mut_self_and(child_id).into_values();
+            child_node.remove_from_layout_node(this_node);
+        }
+        self.remove_from_parent_layout(parent_id);
+
+        let old_node = self.raw.replace_with(node);
+
+        self.attach_to_parent_layout(resources);
+        for &child_id in &child_ids {
+            let (this_node, child_node) = self.get_mut_self_and(child_id).into_values();
+            child_node.apply_measurement_metadata_to_layout(resources, &this_node.computed_styles);
+            child_node.append_to_layout_node(this_node);
+        }
+
+        self.record_layout_hook(&parent_ids, false);
+        old_node
+    }
+
+    pub fn replace_with_subtree_with_layout(&mut self, id: DOMNodeId<E, S, C, L>, resources: &L::Resources) -> Result<(), ()>
+    where
+        L: TLayoutNode<TextMeasureMetadata = C, ImageMeasureMetadata = (), NormalMeasureMetadata = !>
+    {
+        let old_parent_id = self.parent_id();
+        let old_subtree_parent_id = self.get(id).parent_id();
+
+        let parent_ids = [old_parent_id, old_subtree_parent_id];
+        self.record_layout_hook(&parent_ids, true);
+
+        self.raw.replace_with_subtree(id).ok_or(())?;
+
+        self.remove_from_parent_layout(old_parent_id);
+        self.get_mut(id)
+            .remove_from_parent_layout(old_subtree_parent_id);
+        self.get_mut(id).attach_to_parent_layout(resources);
+
+        self.record_layout_hook(&parent_ids, false);
+        Ok(())
+    }
+
+    pub fn move_to_with_layout(&mut self, new_parent_id: DOMNodeId<E, S, C, L>, position: usize, resources: &L::Resources) -> Result<(), ()>
+    where
+        L: TLayoutNode<TextMeasureMetadata = C, ImageMeasureMetadata = (), NormalMeasureMetadata = !>
+    {
+        let old_parent_id = self.parent_id();
+
+        let parent_ids = [old_parent_id, Some(new_parent_id)];
+        self.record_layout_hook(&parent_ids, true);
+
+        self.raw.move_to(new_parent_id, position).ok_or(())?;
+
+        self.remove_from_parent_layout(old_parent_id);
+        self.attach_to_parent_layout(resources);
+
+        self.record_layout_hook(&parent_ids, false);
+        Ok(())
+    }
+
+    pub fn detach_range_with_layout(
+        &mut self,
+        first_id: DOMNodeId<E, S, C, L>,
+        last_id: DOMNodeId<E, S, C, L>
+    ) -> Result<Vec<DOMNodeId<E, S, C, L>>, ()> {
+        let ids = self.children_range(first_id, last_id).ok_or(())?;
+
+        let parent_ids = [Some(self.id())];
+        self.record_layout_hook(&parent_ids, true);
+
+        for &id in &ids {
+            let (this_node, child_node) = self.get_mut_self_and(id).into_values();
+            child_node.remove_from_layout_node(this_node);
+        }
+        self.raw.detach_range(first_id, last_id).ok_or(())?;
+
+        self.record_layout_hook(&parent_ids, false);
+        Ok(ids)
+    }
+
+    pub fn move_range_after_with_layout(
+        &mut self,
+        first_id: DOMNodeId<E, S, C, L>,
+        last_id: DOMNodeId<E, S, C, L>,
+        anchor_id: DOMNodeId<E, S, C, L>,
+        resources: &L::Resources
+    ) -> Result<(), ()>
+    where
+        L: TLayoutNode<TextMeasureMetadata = C, ImageMeasureMetadata = (), NormalMeasureMetadata = !>
+    {
+        let ids = self.children_range(first_id, last_id).ok_or(())?;
+        let new_parent_id = self.get(anchor_id).parent_id();
+
+        let parent_ids = [Some(self.id()), new_parent_id];
+        self.record_layout_hook(&parent_ids, true);
+
+        self.raw
+            .move_range_after(first_id, last_id, anchor_id)
+            .ok_or(())?;
+
+        let old_parent_id = Some(self.id());
+        for &id in &ids {
+            self.get_mut(id).remove_from_parent_layout(old_parent_id);
+        }
+        // Reattach front to back so that each layout index is already valid.
+        for &id in &ids {
+            self.get_mut(id).attach_to_parent_layout(resources);
+        }
+
+        self.record_layout_hook(&parent_ids, false);
+        Ok(())
+    }
+
+    pub fn sort_children_by_with_layout<F>(&mut self, compare: F) -> Result<(), ()>
+    where
+        F: FnMut(&DOMNode<E, S, C, L>, &DOMNode<E, S, C, L>) -> Ordering
+    {
+        self.reorder_with_layout(|raw| raw.sort_children_by(compare))
+    }
+
+    pub fn sort_children_by_key_with_layout<K, F>(&mut self, f: F) -> Result<(), ()>
+    where
+        K: Ord,
+        F: FnMut(&DOMNode<E, S, C, L>) -> K
+    {
+        self.reorder_with_layout(|raw| raw.sort_children_by_key(f))
+    }
+
+    pub fn reverse_children_with_layout(&mut self) -> Result<(), ()> {
+        self.reorder_with_layout(|raw| raw.reverse_children())
+    }
+
+    pub fn reorder_children_with_layout(&mut self, ids: &[DOMNodeId<E, S, C, L>]) -> Result<(), ()> {
+        self.reorder_with_layout(|raw| raw.reorder_children(ids))
+    }
+
+    pub fn retain_descendants_with_layout<F>(&mut self, mut f: F, resources: &L::Resources) -> usize
+    where
+        F: FnMut(DOMArenaRef<E, S, C, L>) -> bool,
+        L: TLayoutNode<TextMeasureMetadata = C, ImageMeasureMetadata = (), NormalMeasureMetadata = !>
+    {
+        self.filter_descendants_with_layout(
+            |node| match f(node) {
+                true => FilterAction::Keep,
+                false => FilterAction::Remove
+            },
+            resources
+        )
+    }
+
+    // Same walk as `filter_descendants`. Removed subtrees leave their parent's layout
+    // before being deallocated, and the children of unwrapped nodes are measured again
+    // under their new parent.
+    pub fn filter_descendants_with_layout<F>(&mut self, mut f: F, resources: &L::Resources) -> usize
+    where
+        F: FnMut(DOMArenaRef<E, S, C, L>) -> FilterAction,
+        L: TLayoutNode<TextMeasureMetadata = C, ImageMeasureMetadata = (), NormalMeasureMetadata = !>
+    {
+        let mut stack = self.child_ids();
+        stack.reverse();
+        let mut removed = 0;
+
+        while let Some(id) = stack.pop() {
+            let action = f(self.get(id));
+            let mut child_ids = self.get_mut(id).child_ids();
+            child_ids.reverse();
+
+            match action {
+                FilterAction::Keep => {
+                    stack.extend(child_ids);
+                }
+                FilterAction::Remove => {
+                    removed += self.get_mut(id).remove_subtree_with_layout();
+                }
+                FilterAction::Unwrap => {
+                    if self.get_mut(id).unwrap_node_with_layout(resources).is_ok() {
+                        removed += 1;
+                    }
+                    stack.extend(child_ids);
+                }
             }
-            {
+        }
+
+        removed
+    }
+
+    fn remove_subtree_with_layout(&mut self) -> usize {
+        let id = self.id();
+        let parent_id = self.parent_id();
+
+        // The removed nodes leave the arena, so their layout children are recorded too.
+        let mut parent_ids = vec![parent_id];
+        parent_ids.extend(self.raw.tree().get(id).descendants_ids_iter().map(Some));
+        self.record_layout_hook(&parent_ids, true);
+
+        self.remove_from_parent_layout(parent_id);
+        self.raw.tree_mut().split_off(id);
+
+        self.record_layout_hook(&[parent_id], false);
+        parent_ids.len() - 1
+    }
+
+    fn reorder_with_layout<F>(&mut self, reorder: F) -> Result<(), ()>
+    where
+        F: FnOnce(&mut RefMut<'a, DOMNode<E, S, C, L>>) -> Option<()>
+    {
+        let parent_ids = [Some(self.id())];
+        self.record_layout_hook(&parent_ids, true);
+
+        let old_child_ids = self.layout_children_ids(self.id()).ok_or(())?;
+        reorder(&mut self.raw).ok_or(())?;
+        self.reorder_layout_children(&old_child_ids);
+
+        self.record_layout_hook(&parent_ids, false);
+        Ok(())
+    }
+
+    fn child_ids(&self) -> Vec<DOMNodeId<E, S, C, L>> {
+        self.raw.tree().get(self.id()).children_ids_iter().collect()
+    }
+
+    // Only children in the layout have a layout node, so indices are counted among them.
+    fn reorder_layout_children(&mut self, old_child_ids: &[DOMNodeId<E, S, C, L>]) {
+        let id = self.id();
+        let new_child_ids = self.layout_children_ids(id).unwrap_or_default();
+        let old_indices: FnvHashMap<_, _> = old_child_ids
+            .iter()
+            .enumerate()
+            .map(|(index, &id)| (id, index))
+            .collect();
+        let old_positions: Vec<_> = new_child_ids.iter().map(|id| old_indices[id]).collect();
+
+        // Layout nodes can only be moved one child at a time, so the layout is reordered
+        // in a single batch with the fewest moves: children that are still in relative
+        // order keep their layout nodes in place, and only the others are taken out and
+        // put back at their new index. A reverse still has to move all but one of them.
+        let stable = longest_increasing_subsequence(&old_positions);
+
+        for (index, &child_id) in new_child_ids.iter().enumerate() {
+            if !stable[index] {
                 let (this_node, child_node) = self.get_mut_self_and(child_id).into_values();
-                child_node.apply_measurement_metadata_to_layout(resources, &this_node.computed_styles);
-                child_node.append_to_layout_node(this_node);
+                child_node.remove_from_layout_node(this_node);
             }
-            next_child_id = self.get(child_id).next_sibling_id();
         }
+        for (index, &child_id) in new_child_ids.iter().enumerate() {
+            if !stable[index] {
+                let (this_node, child_node) = self.get_mut_self_and(child_id).into_values();
+                child_node.insert_into_layout_node(this_node, index);
+            }
+        }
+    }
+
+    // Lets a transaction on the tree undo the layout changes as well, see `DOMTree::undo`.
+    // Record once with `attach` set before changing any links, and once unset after.
+    pub(crate) fn record_layout_hook(&mut self, parent_ids: &[Option<DOMNodeId<E, S, C, L>>], attach: bool) {
+        if !self.raw.tree().is_recording() {
+            return;
+        }
+        let mut ids: Vec<_> = parent_ids.iter().filter_map(|&id| id).collect();
+        ids.dedup();
+        let parents = ids
+            .into_iter()
+            .filter_map(|id| Some((id, self.layout_children_ids(id)?)))
+            .collect();
+        self.raw.tree_mut().record_hook(parents, attach);
+    }
+
+    fn layout_children_ids(&self, id: DOMNodeId<E, S, C, L>) -> Option<Vec<DOMNodeId<E, S, C, L>>> {
+        let node = self.raw.tree().get(id);
+        node.try_value()?;
+        let ids = node
+            .children_refs_iter()
+            .filter(|child| child.try_value().map_or(false, |child| child.in_layout))
+            .map(|child| child.id())
+            .collect();
+        Some(ids)
+    }
+
+    // Siblings that aren't in the layout have no layout node, so they're skipped when
+    // counting where this node goes among its parent's layout children.
+    fn layout_index(&self) -> Option<usize> {
+        let tree = self.raw.tree();
+        let node = tree.get(self.id());
+        node.parent_id()?;
+
+        let mut index = 0;
+        let mut sibling = node.prev_sibling();
+        while let Some(node) = sibling {
+            if node.try_value()?.in_layout {
+                index += 1;
+            }
+            sibling = node.prev_sibling();
+        }
+        Some(index)
+    }
+
+    pub(crate) fn remove_from_parent_layout(&mut self, parent_id: Option<DOMNodeId<E, S, C, L>>) {
+        let id = self.id();
+        if let Some(parent_id) = parent_id {
+            let (parent_node, this_node) = self.get_mut_pair((parent_id, id)).into_values();
+            this_node.remove_from_layout_node(parent_node);
+        }
+    }
+
+    pub(crate) fn attach_to_parent_layout(&mut self, resources: &L::Resources)
+    where
+        L: TLayoutNode<TextMeasureMetadata = C, ImageMeasureMetadata = (), NormalMeasureMetadata = !>
+    {
+        let id = self.id();
+        let parent_id = self.parent_id();
+        let index = self.layout_index();
+        if let (Some(parent_id), Some(index)) = (parent_id, index) {
+            let (parent_node, this_node) = self.get_mut_pair((parent_id, id)).into_values();
+            this_node.apply_measurement_metadata_to_layout(resources, &parent_node.computed_styles);
+            this_node.insert_into_layout_node(parent_node, index);
+        }
+    }
+
+    pub fn build_layout(&mut self, resources: &L::Resources)
+    where
+        L: TLayoutNode<TextMeasureMetadata = C, ImageMeasureMetadata = (), NormalMeasureMetadata = !>
+    {
+        self.walk_mut(
+            |node| {
+                debug_assert_eq!(node.layout_node().is_tainted(), false);
+                Walk::Continue
+            },
+            |mut node| {
+                if let (child_node, Some(this_node)) = node.values_with_parent() {
+                    child_node.apply_measurement_metadata_to_layout(resources, &this_node.computed_styles);
+                    child_node.append_to_layout_node(this_node);
+                }
+                Walk::Continue
+            }
+        );
+    }
+}
+
+fn longest_increasing_subsequence(values: &[usize]) -> Vec<bool> {
+    let mut tails: Vec<usize> = vec![];
+    let mut prev_indices = vec![None; values.len()];
+
+    for (index, &value) in values.iter().enumerate() {
+        let position = match tails.binary_search_by(|&tail| values[tail].cmp(&value)) {
+            Ok(position) | Err(position) => position
+        };
+        if position > 0 {
+            prev_indices[index] = Some(tails[position - 1]);
+        }
+        if position == tails.len() {
+            tails.push(index);
+        } else {
+            tails[position] = index;
+        }
+    }
+
+    let mut in_subsequence = vec![false; values.len()];
+    let mut next_index = tails.last().cloned();
+    while let Some(index) = next_index {
+        in_subsequence[index] = true;
+        next_index = prev_indices[index];
     }
+    in_subsequence
 }
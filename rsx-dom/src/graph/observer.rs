@@ -0,0 +1,102 @@
+/*
+Copyright 2016 Mozilla
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+this file except in compliance with the License. You may obtain a copy of the
+License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed
+under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+CONDITIONS OF ANY KIND, either express or implied. See the License for the
+specific language governing permissions and limitations under the License.
+*/
+
+pub use rsx_tree::types::ObserverId;
+use rsx_tree::types::{MutationRecord, Tree};
+
+use types::{DOMChildren, DOMNode, DOMNodeId};
+
+#[derive(Debug, PartialEq)]
+pub enum DOMMutationRecord<E, S, C, L> {
+    ChildList {
+        target: DOMNodeId<E, S, C, L>,
+        added_nodes: DOMChildren<E, S, C, L>,
+        removed_nodes: DOMChildren<E, S, C, L>,
+        previous_sibling: Option<DOMNodeId<E, S, C, L>>,
+        next_sibling: Option<DOMNodeId<E, S, C, L>>
+    },
+    Attributes {
+        target: DOMNodeId<E, S, C, L>
+    },
+    CharacterData {
+        target: DOMNodeId<E, S, C, L>
+    }
+}
+
+impl<E, S, C, L> DOMMutationRecord<E, S, C, L> {
+    pub(crate) fn from_raw(record: &MutationRecord<DOMNode<E, S, C, L>>, tree: &Tree<DOMNode<E, S, C, L>>) -> Option<Self> {
+        use self::MutationRecord::*;
+
+        match *record {
+            Append {
+                parent_id,
+                child_id
+            }
+            | Prepend {
+                parent_id,
+                child_id
+            } => Some(Self::added(tree, parent_id, child_id)),
+            InsertBefore {
+                sibling_id,
+                child_id
+            }
+            | InsertAfter {
+                sibling_id,
+                child_id
+            } => {
+                let parent_id = tree.get(sibling_id).parent_id()?;
+                Some(Self::added(tree, parent_id, child_id))
+            }
+            Detach {
+                parent_id,
+                child_id,
+                prev_sibling_id,
+                next_sibling_id
+            } => Some(DOMMutationRecord::ChildList {
+                target: parent_id,
+                added_nodes: vec![],
+                removed_nodes: vec![child_id],
+                previous_sibling: prev_sibling_id,
+                next_sibling: next_sibling_id
+            }),
+            Reorder { parent_id } => {
+                // There's no DOM equivalent, so report every child as removed and re-added.
+                let child_ids: Vec<_> = tree.get(parent_id).children_ids_iter().collect();
+                Some(DOMMutationRecord::ChildList {
+                    target: parent_id,
+                    added_nodes: child_ids.clone(),
+                    removed_nodes: child_ids,
+                    previous_sibling: None,
+                    next_sibling: None
+                })
+            }
+            ValueMut { id } => {
+                if tree.get(id).try_value()?.data.is_text().is_some() {
+                    Some(DOMMutationRecord::CharacterData { target: id })
+                } else {
+                    Some(DOMMutationRecord::Attributes { target: id })
+                }
+            }
+            Remove { .. } => None
+        }
+    }
+
+    fn added(tree: &Tree<DOMNode<E, S, C, L>>, parent_id: DOMNodeId<E, S, C, L>, child_id: DOMNodeId<E, S, C, L>) -> Self {
+        let (previous_sibling, next_sibling) = tree.get(child_id).sibling_ids();
+        DOMMutationRecord::ChildList {
+            target: parent_id,
+            added_nodes: vec![child_id],
+            removed_nodes: vec![],
+            previous_sibling,
+            next_sibling
+        }
+    }
+}
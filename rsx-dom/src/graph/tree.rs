@@ -9,12 +9,14 @@ CONDITIONS OF ANY KIND, either express or implied. See the License for the
 specific language governing permissions and limitations under the License.
 */
 
+use std::hash::Hash;
 use std::ops::{Deref, DerefMut};
 
 use rsx_shared::traits::{TComputedStyles, TDOMTree, TGenericEvent, TLayoutNode, TStyleDeclarations};
+pub use rsx_tree::types::NodePath;
 use rsx_tree::types::Tree;
 
-use types::{DOMArenaRef, DOMArenaRefMut, DOMArenaRefMutPair, DOMNode, DOMNodeId, DOMNodeIdPair};
+use types::{DOMArenaRef, DOMArenaRefMut, DOMArenaRefMutPair, DOMMutationRecord, DOMNode, DOMNodeId, DOMNodeIdPair, ObserverId};
 
 #[derive(Debug, PartialEq)]
 pub struct DOMTree<E, S, C, L> {
@@ -50,6 +52,10 @@ impl<E, S, C, L> DerefMut for DOMTree<E, S, C, L> {
 }
 
 impl<E, S, C, L> DOMTree<E, S, C, L> {
+    pub(crate) fn from_inner(raw: Tree<DOMNode<E, S, C, L>>) -> Self {
+        DOMTree { raw }
+    }
+
     pub(crate) fn into_inner(self) -> Tree<DOMNode<E, S, C, L>> {
         self.raw
     }
@@ -89,6 +95,92 @@ impl<E, S, C, L> DOMTree<E, S, C, L> {
     pub fn get_mut_pair(&mut self, ids: DOMNodeIdPair<E, S, C, L>) -> DOMArenaRefMutPair<E, S, C, L> {
         DOMArenaRefMutPair::from(self.raw.get_mut_pair(ids))
     }
+
+    pub fn resolve(&self, path: &NodePath) -> Option<DOMNodeId<E, S, C, L>> {
+        self.raw.resolve(path)
+    }
+
+    pub fn swap_nodes(&mut self, a: DOMNodeId<E, S, C, L>, b: DOMNodeId<E, S, C, L>) -> Option<()> {
+        self.raw.swap_nodes(a, b)
+    }
+
+    pub fn observe<F>(&mut self, mut observer: F) -> ObserverId
+    where
+        E: 'static,
+        S: 'static,
+        C: 'static,
+        L: 'static,
+        F: FnMut(&DOMMutationRecord<E, S, C, L>) + 'static
+    {
+        self.raw.observe(move |record, tree| {
+            if let Some(record) = DOMMutationRecord::from_raw(record, tree) {
+                observer(&record);
+            }
+        })
+    }
+
+    pub fn unobserve(&mut self, id: ObserverId) -> bool {
+        self.raw.unobserve(id)
+    }
+
+    pub fn pin(&mut self, id: DOMNodeId<E, S, C, L>) {
+        self.raw.pin(id)
+    }
+
+    pub fn unpin(&mut self, id: DOMNodeId<E, S, C, L>) -> bool {
+        self.raw.unpin(id)
+    }
+
+    pub fn orphans(&self) -> Vec<DOMNodeId<E, S, C, L>> {
+        self.raw.orphans()
+    }
+
+    pub fn collect_garbage(&mut self) -> usize {
+        self.raw.collect_garbage()
+    }
+}
+
+impl<E, S, C, L> DOMTree<E, S, C, L>
+where
+    S: Hash
+{
+    pub fn enable_subtree_hashes(&mut self) {
+        self.raw.enable_subtree_hashes();
+    }
+
+    pub fn disable_subtree_hashes(&mut self) {
+        self.raw.disable_subtree_hashes();
+    }
+
+    pub fn subtree_hash(&self) -> u64 {
+        self.root().subtree_hash()
+    }
+
+    // Finds the first node in tree order with the same subtree hash. Unless hashes are
+    // enabled, every subtree on the way is hashed from scratch.
+    pub fn find_subtree_by_hash(&self, hash: u64) -> Option<DOMNodeId<E, S, C, L>> {
+        let root_id = self.raw.root();
+        self.raw
+            .get(root_id)
+            .descendants_refs_iter()
+            .find(|node| node.subtree_hash() == hash)
+            .map(|node| node.id())
+    }
+}
+
+impl<E, S, C, L> DOMTree<E, S, C, L>
+where
+    E: TGenericEvent,
+    S: TStyleDeclarations + Clone,
+    C: TComputedStyles<Styles = S>,
+    L: TLayoutNode<Styles = S>
+{
+    pub fn clone_tree(&self) -> Self {
+        let root_id = self.raw.root();
+        DOMTree {
+            raw: self.raw.clone_subtree_with(root_id, DOMNode::rebuild)
+        }
+    }
 }
 
 impl<E, S, C, L> TDOMTree for DOMTree<E, S, C, L>
@@ -126,4 +218,149 @@ where
     {
         self.root_mut().build_layout(resources);
     }
+
+    pub fn swap_nodes_with_layout(&mut self, a: DOMNodeId<E, S, C, L>, b: DOMNodeId<E, S, C, L>, resources: &L::Resources) -> Result<(), ()>
+    where
+        L: TLayoutNode<TextMeasureMetadata = C, ImageMeasureMetadata = (), NormalMeasureMetadata = !>
+    {
+        let old_parent_ids = (self.get(a).parent_id(), self.get(b).parent_id());
+
+        let parent_ids = [old_parent_ids.0, old_parent_ids.1];
+        self.get_mut(a).record_layout_hook(&parent_ids, true);
+
+        self.raw.swap_nodes(a, b).ok_or(())?;
+
+        self.get_mut(a).remove_from_parent_layout(old_parent_ids.0);
+        self.get_mut(b).remove_from_parent_layout(old_parent_ids.1);
+
+        // Reattach in tree order so that each layout index is already valid.
+        let mut ids = [a, b];
+        ids.sort_by_key(|&id| self.get(id).child_index());
+        for &id in &ids {
+            self.get_mut(id).attach_to_parent_layout(resources);
+        }
+
+        self.get_mut(a).record_layout_hook(&parent_ids, false);
+        Ok(())
+    }
+
+    pub fn split_off(&mut self, id: DOMNodeId<E, S, C, L>, resources: &L::Resources) -> Option<Self>
+    where
+        C: TComputedStyles<Styles = S>,
+        L: TLayoutNode<Styles = S, TextMeasureMetadata = C, ImageMeasureMetadata = (), NormalMeasureMetadata = !>
+    {
+        if id == self.raw.root() {
+            return None;
+        }
+        self.raw.get(id).try_value()?;
+        let parent_id = self.get(id).parent_id();
+
+        // The split off nodes leave the arena, so their layout children are recorded too.
+        let mut parent_ids = vec![parent_id];
+        parent_ids.extend(self.raw.get(id).descendants_ids_iter().map(Some));
+        self.root_mut().record_layout_hook(&parent_ids, true);
+
+        self.get_mut(id).remove_from_parent_layout(parent_id);
+
+        let mut tree = DOMTree::default();
+        {
+            let root_id = tree.raw.root();
+            let document_id = tree
+                .raw
+                .get_mut(root_id)
+                .append_split_off(&mut self.raw, id)?;
+            let (root_node, node) = tree.get_mut_pair((root_id, document_id)).into_values();
+            node.apply_measurement_metadata_to_layout(resources, &root_node.computed_styles);
+            node.append_to_layout_node(root_node);
+        }
+
+        self.root_mut().record_layout_hook(&[parent_id], false);
+        Some(tree)
+    }
+
+    pub fn begin(&mut self)
+    where
+        S: TStyleDeclarations + Clone,
+        C: TComputedStyles<Styles = S>,
+        L: TLayoutNode<Styles = S>
+    {
+        self.raw.begin();
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.raw.is_recording()
+    }
+
+    pub fn commit(&mut self) -> bool {
+        self.raw.commit()
+    }
+
+    pub fn rollback(&mut self, resources: &L::Resources) -> bool
+    where
+        L: TLayoutNode<TextMeasureMetadata = C, ImageMeasureMetadata = (), NormalMeasureMetadata = !>
+    {
+        self.raw
+            .rollback_with(|tree, id, child_ids, attach| apply_layout_hook(tree, id, child_ids, attach, resources))
+    }
+
+    pub fn undo(&mut self, resources: &L::Resources) -> bool
+    where
+        L: TLayoutNode<TextMeasureMetadata = C, ImageMeasureMetadata = (), NormalMeasureMetadata = !>
+    {
+        self.raw
+            .undo_with(|tree, id, child_ids, attach| apply_layout_hook(tree, id, child_ids, attach, resources))
+    }
+
+    pub fn redo(&mut self, resources: &L::Resources) -> bool
+    where
+        L: TLayoutNode<TextMeasureMetadata = C, ImageMeasureMetadata = (), NormalMeasureMetadata = !>
+    {
+        self.raw
+            .redo_with(|tree, id, child_ids, attach| apply_layout_hook(tree, id, child_ids, attach, resources))
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.raw.can_undo()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.raw.can_redo()
+    }
+
+    pub fn set_history_limit(&mut self, limit: usize) {
+        self.raw.set_history_limit(limit);
+    }
+
+    pub fn clear_history(&mut self) {
+        self.raw.clear_history();
+    }
+}
+
+// Layout nodes don't know about the tree history, so around every layout-aware edit
+// the layout children of the affected parents are recorded. Reverting detaches the
+// ones from after the edit, and attaches and measures the ones from before again.
+fn apply_layout_hook<E, S, C, L>(
+    tree: &mut Tree<DOMNode<E, S, C, L>>,
+    parent_id: DOMNodeId<E, S, C, L>,
+    child_ids: &[DOMNodeId<E, S, C, L>],
+    attach: bool,
+    resources: &L::Resources
+) where
+    E: TGenericEvent,
+    S: TStyleDeclarations,
+    C: TComputedStyles,
+    L: TLayoutNode<TextMeasureMetadata = C, ImageMeasureMetadata = (), NormalMeasureMetadata = !>
+{
+    for &child_id in child_ids {
+        let (parent_node, child_node) = tree
+            .get_mut_pair((parent_id, child_id))
+            .try_into_values()
+            .expect("Node deallocated");
+        if attach {
+            child_node.apply_measurement_metadata_to_layout(resources, &parent_node.computed_styles);
+            child_node.append_to_layout_node(parent_node);
+        } else {
+            child_node.remove_from_layout_node(parent_node);
+        }
+    }
 }
@@ -0,0 +1,86 @@
+/*
+Copyright 2016 Mozilla
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+this file except in compliance with the License. You may obtain a copy of the
+License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed
+under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+CONDITIONS OF ANY KIND, either express or implied. See the License for the
+specific language governing permissions and limitations under the License.
+*/
+
+use std::ops::{Deref, DerefMut};
+
+pub use rsx_tree::types::Walk;
+use rsx_tree::types::WalkRefMut;
+
+use types::{DOMArenaRef, DOMNode, DOMNodeId};
+
+#[derive(Debug)]
+pub struct DOMWalkRefMut<'a, E: 'a, S: 'a, C: 'a, L: 'a> {
+    raw: WalkRefMut<'a, DOMNode<E, S, C, L>>
+}
+
+impl<'a, E, S, C, L> From<WalkRefMut<'a, DOMNode<E, S, C, L>>> for DOMWalkRefMut<'a, E, S, C, L> {
+    fn from(raw: WalkRefMut<'a, DOMNode<E, S, C, L>>) -> Self {
+        DOMWalkRefMut { raw }
+    }
+}
+
+impl<'a, E, S, C, L> Deref for DOMWalkRefMut<'a, E, S, C, L> {
+    type Target = DOMNode<E, S, C, L>;
+
+    fn deref(&self) -> &Self::Target {
+        self.value()
+    }
+}
+
+impl<'a, E, S, C, L> DerefMut for DOMWalkRefMut<'a, E, S, C, L> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.value_mut()
+    }
+}
+
+impl<'a, E, S, C, L> DOMWalkRefMut<'a, E, S, C, L> {
+    pub(crate) fn value(&self) -> &DOMNode<E, S, C, L> {
+        self.raw.try_value().expect("Node deallocated")
+    }
+
+    pub(crate) fn value_mut(&mut self) -> &mut DOMNode<E, S, C, L> {
+        self.raw.try_value_mut().expect("Node deallocated")
+    }
+
+    pub fn id(&self) -> DOMNodeId<E, S, C, L> {
+        self.raw.id()
+    }
+
+    pub fn depth(&self) -> usize {
+        self.raw.depth()
+    }
+
+    pub fn ancestor_ids(&self) -> &[DOMNodeId<E, S, C, L>] {
+        self.raw.ancestor_ids()
+    }
+
+    pub fn parent(&self) -> Option<DOMArenaRef<E, S, C, L>> {
+        self.raw.parent().map(DOMArenaRef::from)
+    }
+
+    pub fn ancestors(&self) -> impl Iterator<Item = DOMArenaRef<E, S, C, L>> {
+        self.raw.ancestors().map(DOMArenaRef::from)
+    }
+
+    #[cfg_attr(feature = "cargo-clippy", allow(type_complexity))]
+    pub fn values_with_parent(&mut self) -> (&mut DOMNode<E, S, C, L>, Option<&mut DOMNode<E, S, C, L>>) {
+        self.raw
+            .try_values_with_parent()
+            .expect("Nodes deallocated")
+    }
+
+    #[cfg_attr(feature = "cargo-clippy", allow(type_complexity))]
+    pub fn values_with_ancestors(&mut self) -> (&mut DOMNode<E, S, C, L>, Vec<&DOMNode<E, S, C, L>>) {
+        self.raw
+            .try_values_with_ancestors()
+            .expect("Nodes deallocated")
+    }
+}
@@ -0,0 +1,118 @@
+/*
+Copyright 2016 Mozilla
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+this file except in compliance with the License. You may obtain a copy of the
+License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed
+under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+CONDITIONS OF ANY KIND, either express or implied. See the License for the
+specific language governing permissions and limitations under the License.
+*/
+
+use std::hash::{Hash, Hasher};
+use std::mem;
+
+use types::{DOMAttribute, DOMAttributeName, DOMAttributeValue, DOMData, DOMNode, DOMNormalNode, DOMTagName, DOMText, DOMTextNode};
+
+// Nodes are hashed by content only: the tag, the attributes besides event listeners,
+// and the text. Layout and computed styles are left out, and so are closures since
+// they can't be compared anyway. Names from `rsx_shared` are plain enums, so their
+// discriminant is all there is to hash, while props can't be looked into and only
+// count as being there.
+
+impl<E, S, C, L> Hash for DOMNode<E, S, C, L>
+where
+    S: Hash
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.data.hash(state);
+    }
+}
+
+impl<E, S, C, L> Hash for DOMData<E, S, C, L>
+where
+    S: Hash
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        mem::discriminant(self).hash(state);
+        match self {
+            &DOMData::Void => {}
+            &DOMData::ShadowHost(ref tree) => tree.root().subtree_hash().hash(state),
+            &DOMData::Text(DOMTextNode { ref content }) => content.hash(state),
+            &DOMData::Normal(DOMNormalNode {
+                ref tag,
+                ref attributes
+            }) => {
+                tag.hash(state);
+                let attributes: Vec<_> = attributes
+                    .iter()
+                    .filter(|attribute| match attribute.1 {
+                        DOMAttributeValue::EventListener(_) => false,
+                        _ => true
+                    })
+                    .collect();
+                attributes.hash(state);
+            }
+        }
+    }
+}
+
+impl Hash for DOMText {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_ref().hash(state);
+    }
+}
+
+impl Hash for DOMTagName {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        mem::discriminant(self).hash(state);
+        match self {
+            &DOMTagName::KnownName(ref name) => mem::discriminant(name).hash(state),
+            &DOMTagName::Simple(name) => name.hash(state),
+            &DOMTagName::NamedspacedName(namespace, name) => (namespace, name).hash(state)
+        }
+    }
+}
+
+impl<E, S, C, L> Hash for DOMAttribute<E, S, C, L>
+where
+    S: Hash
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        if let DOMAttributeValue::EventListener(_) = self.1 {
+            return;
+        }
+        self.0.hash(state);
+        self.1.hash(state);
+    }
+}
+
+impl Hash for DOMAttributeName {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        mem::discriminant(self).hash(state);
+        match self {
+            &DOMAttributeName::KnownName(ref name) => mem::discriminant(name).hash(state),
+            &DOMAttributeName::EventType(ref name) => mem::discriminant(name).hash(state),
+            &DOMAttributeName::Simple(name) => name.hash(state),
+            &DOMAttributeName::NamedspacedName(namespace, name) => (namespace, name).hash(state)
+        }
+    }
+}
+
+impl<E, S, C, L> Hash for DOMAttributeValue<E, S, C, L>
+where
+    S: Hash
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        mem::discriminant(self).hash(state);
+        match self {
+            &DOMAttributeValue::Boolean(value) => value.hash(state),
+            &DOMAttributeValue::Number(value) => value.to_bits().hash(state),
+            &DOMAttributeValue::Char(value) => value.hash(state),
+            &DOMAttributeValue::Str(ref value) => value.hash(state),
+            &DOMAttributeValue::Styles(ref value) => value.hash(state),
+            &DOMAttributeValue::Prop(_) | &DOMAttributeValue::EventListener(_) => {}
+            &DOMAttributeValue::Node(ref value) => value.hash(state)
+        }
+    }
+}
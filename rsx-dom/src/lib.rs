@@ -30,6 +30,10 @@ pub mod macros;
 mod graph;
 mod convert;
 mod export;
+mod hash;
+#[cfg(feature = "rsx-parse")]
+mod parse;
+mod serialize;
 
 pub mod types;
 pub mod util;
@@ -0,0 +1,234 @@
+/*
+Copyright 2016 Mozilla
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+this file except in compliance with the License. You may obtain a copy of the
+License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed
+under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+CONDITIONS OF ANY KIND, either express or implied. See the License for the
+specific language governing permissions and limitations under the License.
+*/
+
+use std::error::Error;
+use std::fmt;
+
+use rsx_parser;
+use rsx_shared::traits::{TComputedStyles, TGenericEvent, TLayoutNode, TStyleDeclarations};
+
+use types::{
+    DOMArenaRefMut,
+    DOMAttribute,
+    DOMAttributeName,
+    DOMAttributeValue,
+    DOMNode,
+    DOMTagName,
+    DOMTree,
+    KnownAttributeName,
+    KnownElementName,
+    RSXAttribute,
+    RSXAttributeName,
+    RSXAttributeValue,
+    RSXAttributes,
+    RSXChild,
+    RSXElement,
+    RSXElementName,
+    RSXIdentifier,
+    RSXNormalElement,
+    RSXSelfClosingElement
+};
+use util::{known_attribute_str, known_element_str, KNOWN_ATTRIBUTE_NAMES, KNOWN_ELEMENT_NAMES};
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum DOMConvertError {
+    Parse(String),
+    MemberExpression(String),
+    UnknownName(String),
+    SpreadAttribute,
+    CodeBlock
+}
+
+impl fmt::Display for DOMConvertError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &DOMConvertError::Parse(ref error) => write!(f, "Couldn't parse RSX: {}", error),
+            &DOMConvertError::MemberExpression(ref name) => write!(f, "Can't resolve component `{}` at runtime", name),
+            &DOMConvertError::UnknownName(ref name) => write!(f, "Can't create the unknown name `{}` at runtime", name),
+            &DOMConvertError::SpreadAttribute | &DOMConvertError::CodeBlock => f.write_str(self.description())
+        }
+    }
+}
+
+impl Error for DOMConvertError {
+    fn description(&self) -> &str {
+        match self {
+            &DOMConvertError::Parse(_) => "Couldn't parse RSX",
+            &DOMConvertError::MemberExpression(_) => "Can't resolve components at runtime",
+            &DOMConvertError::UnknownName(_) => "Can't create unknown names at runtime",
+            &DOMConvertError::SpreadAttribute => "Can't evaluate spread attributes at runtime",
+            &DOMConvertError::CodeBlock => "Can't evaluate code blocks at runtime"
+        }
+    }
+}
+
+impl<E, S, C, L> DOMTree<E, S, C, L>
+where
+    E: TGenericEvent,
+    S: TStyleDeclarations,
+    C: TComputedStyles<Styles = S>,
+    L: TLayoutNode<Styles = S>
+{
+    pub fn from_rsx(element: &RSXElement) -> Result<Self, DOMConvertError> {
+        let mut tree = DOMTree::default();
+        append_element(&mut tree.root_mut(), element)?;
+        Ok(tree)
+    }
+
+    pub fn from_rsx_str(source: &str) -> Result<Self, DOMConvertError> {
+        let (element, _) = rsx_parser::parse(source).map_err(|error| DOMConvertError::Parse(format!("{:?}", error)))?;
+        DOMTree::from_rsx(&element)
+    }
+}
+
+fn append_element<E, S, C, L>(parent: &mut DOMArenaRefMut<E, S, C, L>, element: &RSXElement) -> Result<(), DOMConvertError>
+where
+    E: TGenericEvent,
+    S: TStyleDeclarations,
+    C: TComputedStyles<Styles = S>,
+    L: TLayoutNode<Styles = S>
+{
+    let (node, children) = convert_element(element)?;
+    let mut parent = parent.append(node);
+    for child in children {
+        match child {
+            &RSXChild::Element(ref element) => append_element(&mut parent, element)?,
+            &RSXChild::Text(ref text) => {
+                parent.append(DOMNode::from(text.0.clone()));
+            }
+            &RSXChild::CodeBlock(_) => return Err(DOMConvertError::CodeBlock)
+        }
+    }
+    Ok(())
+}
+
+fn convert_element<E, S, C, L>(element: &RSXElement) -> Result<(DOMNode<E, S, C, L>, &[RSXChild]), DOMConvertError>
+where
+    E: TGenericEvent,
+    S: TStyleDeclarations,
+    C: TComputedStyles<Styles = S>,
+    L: TLayoutNode<Styles = S>
+{
+    let (name, attributes, children) = match element {
+        &RSXElement::SelfClosing(RSXSelfClosingElement(ref name, ref attributes)) => (name, attributes, &[][..]),
+        &RSXElement::Normal(RSXNormalElement(ref name, ref attributes, ref children)) => (name, attributes, &children.0[..])
+    };
+    let tag = convert_tag_name(name)?;
+    let attributes = convert_attributes(attributes)?;
+    Ok((DOMNode::from((tag, attributes)), children))
+}
+
+fn convert_tag_name(name: &RSXElementName) -> Result<DOMTagName, DOMConvertError> {
+    match name {
+        &RSXElementName::Name(RSXIdentifier(ref name)) => match known_element_name(name) {
+            Some(known) => Ok(DOMTagName::from(known)),
+            None => Ok(DOMTagName::from(static_name(name)?))
+        },
+        &RSXElementName::NamedspacedName(RSXIdentifier(ref namespace), RSXIdentifier(ref name)) => Ok(DOMTagName::from((
+            static_name(namespace)?,
+            static_name(name)?
+        ))),
+        &RSXElementName::MemberExpression(ref path) => {
+            let path: Vec<_> = path.iter().map(|identifier| &identifier.0[..]).collect();
+            Err(DOMConvertError::MemberExpression(path.join(".")))
+        }
+    }
+}
+
+fn convert_attributes<E, S, C, L>(attributes: &RSXAttributes) -> Result<Vec<DOMAttribute<E, S, C, L>>, DOMConvertError>
+where
+    E: TGenericEvent,
+    S: TStyleDeclarations,
+    C: TComputedStyles<Styles = S>,
+    L: TLayoutNode<Styles = S>
+{
+    attributes
+        .0
+        .iter()
+        .map(|attribute| match attribute {
+            &RSXAttribute::Named(ref name, ref value) => Ok(DOMAttribute::from((
+                convert_attribute_name(name)?,
+                convert_attribute_value(value)?
+            ))),
+            &RSXAttribute::Spread(_) => Err(DOMConvertError::SpreadAttribute)
+        })
+        .collect()
+}
+
+fn convert_attribute_name(name: &RSXAttributeName) -> Result<DOMAttributeName, DOMConvertError> {
+    match name {
+        &RSXAttributeName::Name(RSXIdentifier(ref name)) => match known_attribute_name(name) {
+            Some(known) => Ok(DOMAttributeName::from(known)),
+            None => Ok(DOMAttributeName::from(static_name(name)?))
+        },
+        &RSXAttributeName::NamedspacedName(RSXIdentifier(ref namespace), RSXIdentifier(ref name)) => Ok(DOMAttributeName::from((
+            static_name(namespace)?,
+            static_name(name)?
+        )))
+    }
+}
+
+fn convert_attribute_value<E, S, C, L>(value: &RSXAttributeValue) -> Result<DOMAttributeValue<E, S, C, L>, DOMConvertError>
+where
+    E: TGenericEvent,
+    S: TStyleDeclarations,
+    C: TComputedStyles<Styles = S>,
+    L: TLayoutNode<Styles = S>
+{
+    match value {
+        &RSXAttributeValue::Default => Ok(DOMAttributeValue::from(true)),
+        &RSXAttributeValue::Boolean(ref value) => Ok(DOMAttributeValue::from(value.0)),
+        &RSXAttributeValue::Number(ref value) => Ok(DOMAttributeValue::from(value.0)),
+        &RSXAttributeValue::Str(ref value) => Ok(DOMAttributeValue::from(value.0.clone())),
+        &RSXAttributeValue::Element(ref element) => {
+            // Attribute values can only hold a single node, so elements with children
+            // are kept whole inside a shadow host.
+            let (node, children) = convert_element(element)?;
+            if children.is_empty() {
+                Ok(DOMAttributeValue::from(node))
+            } else {
+                Ok(DOMAttributeValue::from(DOMNode::from(DOMTree::from_rsx(
+                    element
+                )?)))
+            }
+        }
+        &RSXAttributeValue::CodeBlock(_) => Err(DOMConvertError::CodeBlock)
+    }
+}
+
+fn known_element_name(name: &str) -> Option<KnownElementName> {
+    KNOWN_ELEMENT_NAMES
+        .iter()
+        .cloned()
+        .find(|&known| known_element_str(known) == name)
+}
+
+fn known_attribute_name(name: &str) -> Option<KnownAttributeName> {
+    KNOWN_ATTRIBUTE_NAMES
+        .iter()
+        .cloned()
+        .find(|&known| known_attribute_str(known) == name)
+}
+
+// Tag and attribute names are `&'static str`, so names only known at runtime have to
+// be found in the known tables, whether as element or attribute names.
+fn static_name(name: &str) -> Result<&'static str, DOMConvertError> {
+    KNOWN_ELEMENT_NAMES
+        .iter()
+        .map(|&known| known_element_str(known))
+        .chain(
+            KNOWN_ATTRIBUTE_NAMES
+                .iter()
+                .map(|&known| known_attribute_str(known))
+        )
+        .find(|&known| known == name)
+        .ok_or_else(|| DOMConvertError::UnknownName(name.to_string()))
+}
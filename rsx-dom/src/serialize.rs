@@ -0,0 +1,369 @@
+/*
+Copyright 2016 Mozilla
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+this file except in compliance with the License. You may obtain a copy of the
+License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed
+under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+CONDITIONS OF ANY KIND, either express or implied. See the License for the
+specific language governing permissions and limitations under the License.
+*/
+
+use std::borrow::Cow;
+use std::fmt::{self, Write};
+use std::io;
+
+use types::{
+    DOMArenaRef,
+    DOMAttribute,
+    DOMAttributeName,
+    DOMAttributeValue,
+    DOMData,
+    DOMForest,
+    DOMNode,
+    DOMNormalNode,
+    DOMTagName,
+    DOMTextNode,
+    KnownElementName
+};
+use util::{known_attribute_str, known_element_str};
+
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "image", "img", "input", "link", "meta", "param", "source", "track", "wbr"
+];
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum MarkupSyntax {
+    Html,
+    Xml
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ShadowHostMode {
+    Flatten,
+    Emit
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct SerializeOptions {
+    pub syntax: MarkupSyntax,
+    pub shadow_hosts: ShadowHostMode,
+    pub indent: Option<usize>
+}
+
+impl Default for SerializeOptions {
+    fn default() -> Self {
+        SerializeOptions {
+            syntax: MarkupSyntax::Html,
+            shadow_hosts: ShadowHostMode::Flatten,
+            indent: None
+        }
+    }
+}
+
+// Style declarations are opaque in `rsx_shared`, so they list themselves as property
+// and value pairs to be written out in a `style` attribute.
+pub trait TSerializeStyles {
+    fn declarations(&self) -> Vec<(String, String)>;
+}
+
+impl TSerializeStyles for () {
+    fn declarations(&self) -> Vec<(String, String)> {
+        vec![]
+    }
+}
+
+impl SerializeOptions {
+    pub fn minified() -> Self {
+        SerializeOptions::default()
+    }
+
+    pub fn pretty() -> Self {
+        SerializeOptions {
+            indent: Some(2),
+            ..SerializeOptions::default()
+        }
+    }
+}
+
+impl<'a, E, S, C, L> DOMArenaRef<'a, E, S, C, L>
+where
+    S: TSerializeStyles
+{
+    pub fn outer_html(&self, options: SerializeOptions) -> String {
+        let mut markup = String::new();
+        self.write_outer_html(&mut markup, options)
+            .expect("Writing to a string can't fail");
+        markup
+    }
+
+    pub fn inner_html(&self, options: SerializeOptions) -> String {
+        let mut markup = String::new();
+        self.write_inner_html(&mut markup, options)
+            .expect("Writing to a string can't fail");
+        markup
+    }
+
+    pub fn write_outer_html<W>(&self, out: &mut W, options: SerializeOptions) -> fmt::Result
+    where
+        W: Write
+    {
+        Serializer::new(out, options).node(self.into_value(), self.children_refs(), 0)
+    }
+
+    pub fn write_inner_html<W>(&self, out: &mut W, options: SerializeOptions) -> fmt::Result
+    where
+        W: Write
+    {
+        let mut serializer = Serializer::new(out, options);
+        for child in self.children_refs() {
+            serializer.node(child.into_value(), child.children_refs(), 0)?;
+        }
+        Ok(())
+    }
+
+    pub fn write_outer_html_io<W>(&self, out: &mut W, options: SerializeOptions) -> io::Result<()>
+    where
+        W: io::Write
+    {
+        let mut writer = IoWriter {
+            inner: out,
+            error: None
+        };
+        let result = self.write_outer_html(&mut writer, options);
+        writer.into_result(result)
+    }
+
+    pub fn write_inner_html_io<W>(&self, out: &mut W, options: SerializeOptions) -> io::Result<()>
+    where
+        W: io::Write
+    {
+        let mut writer = IoWriter {
+            inner: out,
+            error: None
+        };
+        let result = self.write_inner_html(&mut writer, options);
+        writer.into_result(result)
+    }
+}
+
+impl<E, S, C, L> DOMForest<E, S, C, L>
+where
+    S: TSerializeStyles
+{
+    pub fn outer_html(&self, options: SerializeOptions) -> String {
+        let mut markup = String::new();
+        self.write_outer_html(&mut markup, options)
+            .expect("Writing to a string can't fail");
+        markup
+    }
+
+    // Roots are written one after the other, like the children of an element.
+    pub fn write_outer_html<W>(&self, out: &mut W, options: SerializeOptions) -> fmt::Result
+    where
+        W: Write
+    {
+        let mut serializer = Serializer::new(out, options);
+        for root in self.roots_refs_iter() {
+            serializer.node(root.into_value(), root.children_refs(), 0)?;
+        }
+        Ok(())
+    }
+}
+
+struct Serializer<'w, W: 'w> {
+    out: &'w mut W,
+    options: SerializeOptions,
+    started: bool
+}
+
+impl<'w, W> Serializer<'w, W>
+where
+    W: Write
+{
+    fn new(out: &'w mut W, options: SerializeOptions) -> Self {
+        Serializer {
+            out,
+            options,
+            started: false
+        }
+    }
+
+    fn node<'a, E: 'a, S: 'a, C: 'a, L: 'a, I>(&mut self, node: &'a DOMNode<E, S, C, L>, children: I, depth: usize) -> fmt::Result
+    where
+        S: TSerializeStyles,
+        I: IntoIterator<Item = DOMArenaRef<'a, E, S, C, L>>
+    {
+        match node.data {
+            DOMData::Void => Ok(()),
+            DOMData::Text(DOMTextNode { ref content }) => {
+                self.line(depth)?;
+                escape(self.out, content.as_ref(), false)
+            }
+            DOMData::ShadowHost(ref tree) => {
+                let root = tree.root();
+                match self.options.shadow_hosts {
+                    ShadowHostMode::Flatten => self.node(root.into_value(), root.children_refs(), depth)?,
+                    ShadowHostMode::Emit => {
+                        self.line(depth)?;
+                        self.out.write_str("<template shadowrootmode=\"open\">")?;
+                        self.node(root.into_value(), root.children_refs(), depth + 1)?;
+                        self.line(depth)?;
+                        self.out.write_str("</template>")?;
+                    }
+                }
+                self.children(children, depth)
+            }
+            DOMData::Normal(DOMNormalNode {
+                ref tag,
+                ref attributes
+            }) => {
+                // Fragments have no markup of their own, only their children do.
+                if tag == &DOMTagName::KnownName(KnownElementName::Fragment) {
+                    return self.children(children, depth);
+                }
+
+                let name = tag_name(tag);
+                self.line(depth)?;
+                write!(self.out, "<{}", name)?;
+                for attribute in attributes {
+                    self.attribute(attribute)?;
+                }
+
+                let mut children = children.into_iter().peekable();
+                if children.peek().is_none() {
+                    match self.options.syntax {
+                        MarkupSyntax::Xml => return self.out.write_str("/>"),
+                        MarkupSyntax::Html if VOID_ELEMENTS.contains(&&*name) => return self.out.write_str(">"),
+                        MarkupSyntax::Html => return write!(self.out, "></{}>", name)
+                    }
+                }
+
+                self.out.write_str(">")?;
+                self.children(children, depth + 1)?;
+                self.line(depth)?;
+                write!(self.out, "</{}>", name)
+            }
+        }
+    }
+
+    fn children<'a, E: 'a, S: 'a, C: 'a, L: 'a, I>(&mut self, children: I, depth: usize) -> fmt::Result
+    where
+        S: TSerializeStyles,
+        I: IntoIterator<Item = DOMArenaRef<'a, E, S, C, L>>
+    {
+        for child in children {
+            self.node(child.into_value(), child.children_refs(), depth)?;
+        }
+        Ok(())
+    }
+
+    fn attribute<E, S, C, L>(&mut self, attribute: &DOMAttribute<E, S, C, L>) -> fmt::Result
+    where
+        S: TSerializeStyles
+    {
+        let name: Cow<str> = match attribute.0 {
+            DOMAttributeName::KnownName(name) => Cow::Borrowed(known_attribute_str(name)),
+            DOMAttributeName::EventType(_) => return Ok(()),
+            DOMAttributeName::Simple(name) => Cow::Borrowed(name),
+            DOMAttributeName::NamedspacedName(namespace, name) => Cow::Owned(format!("{}:{}", namespace, name))
+        };
+
+        // Props have no accessors in `rsx_shared`, so their debug output is the closest
+        // there is to their value.
+        let value = match attribute.1 {
+            DOMAttributeValue::Boolean(false) | DOMAttributeValue::EventListener(_) => return Ok(()),
+            DOMAttributeValue::Boolean(true) if self.options.syntax == MarkupSyntax::Html => {
+                return write!(self.out, " {}", name);
+            }
+            DOMAttributeValue::Boolean(true) => name.to_string(),
+            DOMAttributeValue::Number(value) => value.to_string(),
+            DOMAttributeValue::Char(value) => value.to_string(),
+            DOMAttributeValue::Str(ref value) => value.as_ref().to_string(),
+            DOMAttributeValue::Styles(ref value) => value
+                .declarations()
+                .into_iter()
+                .map(|(property, value)| format!("{}: {}", property, value))
+                .collect::<Vec<_>>()
+                .join("; "),
+            DOMAttributeValue::Prop(ref value) => format!("{:?}", value),
+            DOMAttributeValue::Node(ref node) => {
+                let mut markup = String::new();
+                let options = SerializeOptions {
+                    indent: None,
+                    ..self.options
+                };
+                Serializer::new(&mut markup, options).node(node, Vec::new(), 0)?;
+                markup
+            }
+        };
+
+        write!(self.out, " {}=\"", name)?;
+        escape(self.out, &value, true)?;
+        self.out.write_str("\"")
+    }
+
+    fn line(&mut self, depth: usize) -> fmt::Result {
+        if let Some(indent) = self.options.indent {
+            if self.started {
+                self.out.write_str("\n")?;
+            }
+            for _ in 0..indent * depth {
+                self.out.write_str(" ")?;
+            }
+        }
+        self.started = true;
+        Ok(())
+    }
+}
+
+struct IoWriter<'w, W: 'w> {
+    inner: &'w mut W,
+    error: Option<io::Error>
+}
+
+impl<'w, W> IoWriter<'w, W> {
+    fn into_result(self, result: fmt::Result) -> io::Result<()> {
+        match (result, self.error) {
+            (Ok(()), _) => Ok(()),
+            (Err(_), Some(error)) => Err(error),
+            (Err(_), None) => Err(io::Error::new(io::ErrorKind::Other, "Formatter error"))
+        }
+    }
+}
+
+impl<'w, W> Write for IoWriter<'w, W>
+where
+    W: io::Write
+{
+    fn write_str(&mut self, string: &str) -> fmt::Result {
+        self.inner.write_all(string.as_bytes()).map_err(|error| {
+            self.error = Some(error);
+            fmt::Error
+        })
+    }
+}
+
+fn tag_name(tag: &DOMTagName) -> Cow<'static, str> {
+    match tag {
+        &DOMTagName::KnownName(name) => Cow::Borrowed(known_element_str(name)),
+        &DOMTagName::Simple(name) => Cow::Borrowed(name),
+        &DOMTagName::NamedspacedName(namespace, name) => Cow::Owned(format!("{}:{}", namespace, name))
+    }
+}
+
+fn escape<W>(out: &mut W, string: &str, in_attribute: bool) -> fmt::Result
+where
+    W: Write
+{
+    for c in string.chars() {
+        match c {
+            '&' => out.write_str("&amp;")?,
+            '<' => out.write_str("&lt;")?,
+            '>' => out.write_str("&gt;")?,
+            '"' if in_attribute => out.write_str("&quot;")?,
+            c => out.write_char(c)?
+        }
+    }
+    Ok(())
+}
@@ -11,9 +11,12 @@ specific language governing permissions and limitations under the License.
 
 use std::borrow::{Borrow, Cow};
 use std::cmp::Ordering;
+use std::mem;
 use std::rc::Rc;
+use std::sync::atomic::{self, AtomicUsize, ATOMIC_USIZE_INIT};
 
 use rsx_shared::traits::{TComputedStyles, TDOMNode, TDOMText, TGenericEvent, TLayoutNode, TStyleDeclarations};
+use rsx_tree::types::Snapshot;
 
 #[cfg(feature = "rsx-parse")]
 pub use rsx_parser::types::*;
@@ -22,15 +25,24 @@ pub use rsx_shared::types::{Closure, EventType, KnownAttributeName, KnownElement
 use util::{find_src, is_event_listener, is_style};
 
 pub use graph::*;
+#[cfg(feature = "rsx-parse")]
+pub use parse::*;
+pub use serialize::*;
 
 pub type DOMAttributes<E, S, C, L> = Vec<DOMAttribute<E, S, C, L>>;
 pub type DOMChildren<E, S, C, L> = Vec<DOMNodeId<E, S, C, L>>;
 
+// Handles are unique across all nodes, so one can't accidentally remove a listener
+// from a node other than the one it was added to.
+static NEXT_LISTENER_HANDLE: AtomicUsize = ATOMIC_USIZE_INIT;
+
 #[derive(Debug, PartialEq)]
 pub struct DOMNode<E, S, C, L> {
     pub(crate) data: DOMData<E, S, C, L>,
+    pub(crate) listeners: Vec<DOMListener<E>>,
     pub(crate) computed_styles: C,
-    pub(crate) layout_node: L
+    pub(crate) layout_node: L,
+    pub(crate) in_layout: bool
 }
 
 impl<E, S, C, L> Default for DOMNode<E, S, C, L>
@@ -66,10 +78,41 @@ where
         DOMNode {
             computed_styles,
             layout_node,
+            listeners: vec![],
+            in_layout: false,
             data
         }
     }
 
+    pub(crate) fn rebuild(&self) -> Self
+    where
+        S: Clone
+    {
+        // Cloned listeners are new listeners, so they get handles of their own.
+        let mut node = DOMNode::new(self.data.clone());
+        for listener in &self.listeners {
+            node.add_event_listener(
+                listener.ty.clone(),
+                listener.callback.clone(),
+                listener.options
+            );
+        }
+        node
+    }
+
+    fn refresh_styles(&mut self) {
+        let user_agent_styles = S::make_user_agent_styles(&self.data);
+
+        self.computed_styles.reset_custom_styles(&self.data);
+        self.computed_styles.apply_styles(&user_agent_styles);
+        self.computed_styles.apply_rules(self.data.get_styles());
+
+        self.layout_node.reset_custom_styles(&self.data);
+        self.layout_node.apply_styles(&user_agent_styles);
+        self.layout_node.apply_rules(self.data.get_styles());
+        self.layout_node.mark_dirty();
+    }
+
     pub fn shadow_dom(self) -> DOMTree<E, S, C, L> {
         match self.data {
             DOMData::ShadowHost(tree) => tree,
@@ -78,6 +121,32 @@ where
     }
 }
 
+// The layout node of a restored node stays in place, since it's linked into the layout
+// of its parent, and only the styles are brought up to date.
+impl<E, S, C, L> Snapshot for DOMNode<E, S, C, L>
+where
+    E: TGenericEvent,
+    S: TStyleDeclarations + Clone,
+    C: TComputedStyles<Styles = S>,
+    L: TLayoutNode<Styles = S>
+{
+    fn snapshot(&self) -> Self {
+        let mut node = DOMNode::new(self.data.clone());
+        node.listeners = self.listeners.clone();
+        node
+    }
+
+    fn restore(&mut self, snapshot: Self) -> Self {
+        let DOMNode {
+            data, listeners, ..
+        } = snapshot;
+        let mut old_node = DOMNode::new(mem::replace(&mut self.data, data));
+        old_node.listeners = mem::replace(&mut self.listeners, listeners);
+        self.refresh_styles();
+        old_node
+    }
+}
+
 impl<E, S, C, L> TDOMNode for DOMNode<E, S, C, L>
 where
     E: TGenericEvent,
@@ -228,12 +297,107 @@ where
         let parent = &mut parent.layout_node;
         let child = &mut self.layout_node;
         parent.append_child(child);
+        self.in_layout = true;
+    }
+
+    pub(crate) fn insert_into_layout_node(&mut self, parent: &mut DOMNode<E, S, C, L>, index: usize) {
+        let parent = &mut parent.layout_node;
+        let child = &mut self.layout_node;
+        parent.insert_child(child, index);
+        self.in_layout = true;
     }
 
     pub(crate) fn remove_from_layout_node(&mut self, parent: &mut DOMNode<E, S, C, L>) {
         let parent = &mut parent.layout_node;
         let child = &mut self.layout_node;
         parent.remove_child(child);
+        self.in_layout = false;
+    }
+}
+
+impl<E, S, C, L> DOMNode<E, S, C, L> {
+    pub fn add_event_listener(&mut self, ty: EventType, callback: Closure<E>, options: ListenerOptions) -> ListenerHandle {
+        let handle = ListenerHandle(NEXT_LISTENER_HANDLE.fetch_add(1, atomic::Ordering::Relaxed));
+        self.listeners.push(DOMListener {
+            handle,
+            ty,
+            callback,
+            options
+        });
+        handle
+    }
+
+    pub fn remove_event_listener(&mut self, handle: ListenerHandle) -> Option<DOMListener<E>> {
+        let index = self
+            .listeners
+            .iter()
+            .position(|listener| listener.handle == handle)?;
+        Some(self.listeners.remove(index))
+    }
+
+    #[cfg_attr(feature = "cargo-clippy", allow(needless_lifetimes))]
+    pub fn listeners_for<'a>(&'a self, ty: EventType) -> impl Iterator<Item = &'a DOMListener<E>> {
+        self.listeners
+            .iter()
+            .filter(move |listener| listener.ty == ty)
+    }
+
+    // Drops both the added listeners and the ones stored as attributes.
+    pub fn remove_all_listeners(&mut self) {
+        self.listeners.clear();
+        if let Some(attributes) = self.data.attributes_mut() {
+            attributes.retain(|i| is_event_listener(i).is_none());
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub struct ListenerHandle(usize);
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct ListenerOptions {
+    pub once: bool,
+    pub capture: bool,
+    pub passive: bool
+}
+
+#[derive(Debug, PartialEq)]
+pub struct DOMListener<E> {
+    pub(crate) handle: ListenerHandle,
+    pub(crate) ty: EventType,
+    pub(crate) callback: Closure<E>,
+    pub(crate) options: ListenerOptions
+}
+
+impl<E> Clone for DOMListener<E>
+where
+    E: TGenericEvent
+{
+    fn clone(&self) -> Self {
+        DOMListener {
+            handle: self.handle,
+            ty: self.ty.clone(),
+            callback: self.callback.clone(),
+            options: self.options
+        }
+    }
+}
+
+impl<E> DOMListener<E> {
+    pub fn handle(&self) -> ListenerHandle {
+        self.handle
+    }
+
+    pub fn event_type(&self) -> &EventType {
+        &self.ty
+    }
+
+    pub fn callback(&self) -> &Closure<E> {
+        &self.callback
+    }
+
+    pub fn options(&self) -> ListenerOptions {
+        self.options
     }
 }
 
@@ -280,6 +444,23 @@ pub enum DOMData<E, S, C, L> {
     Normal(DOMNormalNode<E, S, C, L>)
 }
 
+impl<E, S, C, L> Clone for DOMData<E, S, C, L>
+where
+    E: TGenericEvent,
+    S: TStyleDeclarations + Clone,
+    C: TComputedStyles<Styles = S>,
+    L: TLayoutNode<Styles = S>
+{
+    fn clone(&self) -> Self {
+        match self {
+            &DOMData::Void => DOMData::Void,
+            &DOMData::ShadowHost(ref tree) => DOMData::ShadowHost(tree.clone_tree()),
+            &DOMData::Text(ref text) => DOMData::Text(text.clone()),
+            &DOMData::Normal(ref normal) => DOMData::Normal(normal.clone())
+        }
+    }
+}
+
 #[allow(dead_code)]
 impl<E, S, C, L> DOMData<E, S, C, L> {
     pub fn text(&self) -> Option<&DOMText> {
@@ -331,12 +512,6 @@ impl<E, S, C, L> DOMData<E, S, C, L> {
         }
     }
 
-    pub(crate) fn drop_event_listeners(&mut self) -> Option<&mut Vec<DOMAttribute<E, S, C, L>>> {
-        let attributes = self.attributes_mut()?;
-        attributes.retain(|i| is_event_listener(i).is_none());
-        Some(attributes)
-    }
-
     pub(crate) fn drop_styles(&mut self) -> Option<&mut Vec<DOMAttribute<E, S, C, L>>> {
         let attributes = self.attributes_mut()?;
         attributes.retain(|i| is_style(i).is_none());
@@ -383,7 +558,7 @@ impl<E, S, C, L> DOMData<E, S, C, L> {
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct DOMTextNode {
     pub(crate) content: DOMText
 }
@@ -394,7 +569,22 @@ pub struct DOMNormalNode<E, S, C, L> {
     pub(crate) attributes: DOMAttributes<E, S, C, L>
 }
 
-#[derive(Debug, PartialEq)]
+impl<E, S, C, L> Clone for DOMNormalNode<E, S, C, L>
+where
+    E: TGenericEvent,
+    S: TStyleDeclarations + Clone,
+    C: TComputedStyles<Styles = S>,
+    L: TLayoutNode<Styles = S>
+{
+    fn clone(&self) -> Self {
+        DOMNormalNode {
+            tag: self.tag.clone(),
+            attributes: self.attributes.clone()
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
 pub enum DOMTagName {
     KnownName(KnownElementName),
     Simple(&'static str),
@@ -404,7 +594,19 @@ pub enum DOMTagName {
 #[derive(Debug, PartialEq)]
 pub struct DOMAttribute<E, S, C, L>(pub DOMAttributeName, pub DOMAttributeValue<E, S, C, L>);
 
-#[derive(Debug, PartialEq)]
+impl<E, S, C, L> Clone for DOMAttribute<E, S, C, L>
+where
+    E: TGenericEvent,
+    S: TStyleDeclarations + Clone,
+    C: TComputedStyles<Styles = S>,
+    L: TLayoutNode<Styles = S>
+{
+    fn clone(&self) -> Self {
+        DOMAttribute(self.0.clone(), self.1.clone())
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
 pub enum DOMAttributeName {
     KnownName(KnownAttributeName),
     EventType(EventType),
@@ -423,3 +625,24 @@ pub enum DOMAttributeValue<E, S, C, L> {
     EventListener(Closure<E>),
     Node(DOMNode<E, S, C, L>)
 }
+
+impl<E, S, C, L> Clone for DOMAttributeValue<E, S, C, L>
+where
+    E: TGenericEvent,
+    S: TStyleDeclarations + Clone,
+    C: TComputedStyles<Styles = S>,
+    L: TLayoutNode<Styles = S>
+{
+    fn clone(&self) -> Self {
+        match self {
+            &DOMAttributeValue::Boolean(value) => DOMAttributeValue::Boolean(value),
+            &DOMAttributeValue::Number(value) => DOMAttributeValue::Number(value),
+            &DOMAttributeValue::Char(value) => DOMAttributeValue::Char(value),
+            &DOMAttributeValue::Str(ref value) => DOMAttributeValue::Str(value.clone()),
+            &DOMAttributeValue::Styles(ref value) => DOMAttributeValue::Styles(value.clone()),
+            &DOMAttributeValue::Prop(ref value) => DOMAttributeValue::Prop(value.clone()),
+            &DOMAttributeValue::EventListener(ref value) => DOMAttributeValue::EventListener(value.clone()),
+            &DOMAttributeValue::Node(ref value) => DOMAttributeValue::Node(value.rebuild())
+        }
+    }
+}
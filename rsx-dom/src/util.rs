@@ -9,14 +9,13 @@ CONDITIONS OF ANY KIND, either express or implied. See the License for the
 specific language governing permissions and limitations under the License.
 */
 
-use types::{Closure, DOMAttribute, DOMAttributeName, DOMAttributeValue, DOMText, EventType, KnownAttributeName};
+use types::{Closure, DOMAttribute, DOMAttributeName, DOMAttributeValue, DOMText, EventType, KnownAttributeName, KnownElementName};
 
 pub fn is_event_listener<E, S, C, L>(attribute: &DOMAttribute<E, S, C, L>) -> Option<&Closure<E>> {
-    use self::DOMAttributeName::KnownName;
+    use self::DOMAttributeName::EventType;
     use self::DOMAttributeValue::EventListener;
-    use self::KnownAttributeName::Style;
     match attribute {
-        &DOMAttribute(KnownName(Style), EventListener(ref l)) => Some(l),
+        &DOMAttribute(EventType(_), EventListener(ref l)) => Some(l),
         _ => None
     }
 }
@@ -71,3 +70,59 @@ where
     let name = DOMAttributeName::KnownName(KnownAttributeName::Style);
     is_style(find_attribute(iter, &name)?)
 }
+
+pub const KNOWN_ELEMENT_NAMES: &[KnownElementName] = &[
+    KnownElementName::Fragment,
+    KnownElementName::Div,
+    KnownElementName::View,
+    KnownElementName::Image,
+    KnownElementName::Span,
+    KnownElementName::Text,
+    KnownElementName::Input,
+    KnownElementName::Button,
+    KnownElementName::Br,
+    KnownElementName::Hr,
+    KnownElementName::Img
+];
+
+pub const KNOWN_ATTRIBUTE_NAMES: &[KnownAttributeName] = &[
+    KnownAttributeName::Src,
+    KnownAttributeName::Style,
+    KnownAttributeName::Id,
+    KnownAttributeName::Class,
+    KnownAttributeName::Href,
+    KnownAttributeName::Width,
+    KnownAttributeName::Height
+];
+
+// Names as written in markup. Matching exhaustively keeps these in step with the names
+// known to `rsx_shared`.
+pub fn known_element_str(name: KnownElementName) -> &'static str {
+    use self::KnownElementName::*;
+    match name {
+        Fragment => "fragment",
+        Div => "div",
+        View => "view",
+        Image => "image",
+        Span => "span",
+        Text => "text",
+        Input => "input",
+        Button => "button",
+        Br => "br",
+        Hr => "hr",
+        Img => "img"
+    }
+}
+
+pub fn known_attribute_str(name: KnownAttributeName) -> &'static str {
+    use self::KnownAttributeName::*;
+    match name {
+        Src => "src",
+        Style => "style",
+        Id => "id",
+        Class => "class",
+        Href => "href",
+        Width => "width",
+        Height => "height"
+    }
+}
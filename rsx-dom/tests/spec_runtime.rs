@@ -17,7 +17,9 @@ specific language governing permissions and limitations under the License.
 extern crate rsx_dom;
 extern crate rsx_shared;
 
+use std::cell::{Cell, RefCell};
 use std::convert::TryInto;
+use std::rc::Rc;
 
 use rsx_dom::types::*;
 use rsx_shared::traits::*;
@@ -32,8 +34,7 @@ use rsx_shared::types::{
     DummyComputedFontStretch,
     DummyComputedFontStyle,
     DummyComputedFontWeight,
-    DummyComputedTextShadow,
-    DummyComputedVisibility
+    DummyComputedTextShadow
 };
 
 type DOMNode = rsx_dom::types::DOMNode<(), (), MockComputedStyles, MockLayoutNode>;
@@ -60,7 +61,7 @@ impl TComputedStyles for MockComputedStyles {
     where
         T: TryInto<KnownElementName>
     {
-        unimplemented!()
+        self.0.push("reset_custom_styles()".to_string());
     }
 
     fn apply_rules<'a, I>(&mut self, _: I)
@@ -146,7 +147,7 @@ impl TInheritedStyles for MockComputedStyles {
     type FontWeight = DummyComputedFontWeight;
     type FontSize = DummyComputedFontSize;
     type FontStretch = DummyComputedFontStretch;
-    type Visibility = DummyComputedVisibility;
+    type Visibility = MockVisibility;
 
     fn inherit_styles(&mut self, _: &Self) {
         self.0.push("inherit_styles()".to_string());
@@ -189,7 +190,7 @@ impl TInheritedStyles for MockComputedStyles {
     }
 
     fn visibility(&self) -> Self::Visibility {
-        unimplemented!()
+        MockVisibility
     }
 
     fn find_font<F, O>(&self, _: F) -> Option<O>
@@ -200,8 +201,26 @@ impl TInheritedStyles for MockComputedStyles {
     }
 }
 
+#[derive(Debug, PartialEq, Clone, Copy)]
+struct MockVisibility;
+
+impl THitTestVisibility for MockVisibility {
+    fn is_hidden(&self) -> bool {
+        false
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+struct MockRect(f32, f32, f32, f32);
+
+impl THitTestRect for MockRect {
+    fn contains_point(&self, x: f32, y: f32) -> bool {
+        x >= self.0 && y >= self.1 && x < self.0 + self.2 && y < self.1 + self.3
+    }
+}
+
 #[derive(Debug, PartialEq, Default)]
-struct MockLayoutNode(Vec<String>);
+struct MockLayoutNode(Vec<String>, MockRect);
 
 impl TLayoutNode for MockLayoutNode {
     type Styles = ();
@@ -210,8 +229,8 @@ impl TLayoutNode for MockLayoutNode {
     type ImageMeasureMetadata = ();
     type NormalMeasureMetadata = !;
     type ReflowDirection = ();
-    type ClientPosition = ();
-    type BoundingClientRect = ();
+    type ClientPosition = MockRect;
+    type BoundingClientRect = MockRect;
     type MeasuredImage = ();
     type ShapedText = ();
 
@@ -226,15 +245,15 @@ impl TLayoutNode for MockLayoutNode {
     where
         T: TryInto<KnownElementName>
     {
-        unimplemented!()
+        self.0.push("reset_custom_styles()".to_string());
     }
 
     fn is_tainted(&self) -> bool {
         false
     }
 
-    fn insert_child(&mut self, _: &mut Self, _: usize) {
-        unimplemented!()
+    fn insert_child(&mut self, _: &mut Self, i: usize) {
+        self.0.push(format!("insert_child({:?})", i));
     }
 
     fn append_child(&mut self, _: &mut Self) {
@@ -242,7 +261,7 @@ impl TLayoutNode for MockLayoutNode {
     }
 
     fn remove_child(&mut self, _: &mut Self) {
-        unimplemented!()
+        self.0.push("remove_child()".to_string());
     }
 
     fn apply_rules<'a, I>(&mut self, _: I)
@@ -257,7 +276,7 @@ impl TLayoutNode for MockLayoutNode {
     }
 
     fn mark_dirty(&mut self) {
-        unimplemented!()
+        self.0.push("mark_dirty()".to_string());
     }
 
     fn measure_self_as_text<T>(&mut self, _: &Self::Resources, t: &T, _: &Self::TextMeasureMetadata)
@@ -283,16 +302,16 @@ impl TLayoutNode for MockLayoutNode {
         self.0.push(string);
     }
 
-    fn set_computed_client_position(&mut self, _: Self::ClientPosition) {
-        unimplemented!()
+    fn set_computed_client_position(&mut self, rect: Self::ClientPosition) {
+        self.1 = rect;
     }
 
     fn get_local_bounding_client_rect(&self) -> Self::BoundingClientRect {
-        unimplemented!()
+        self.1
     }
 
     fn get_global_bounding_client_rect(&self) -> Self::BoundingClientRect {
-        unimplemented!()
+        self.1
     }
 
     fn get_measured_image(&self) -> &Self::MeasuredImage {
@@ -622,32 +641,1172 @@ fn test_simple_layout() {
                 "append_child()".to_string(),
                 "append_child()".to_string(),
                 "append_child()".to_string(),
-            ]),
+            ], MockRect::default()),
             &MockLayoutNode(vec![
                 "apply_styles()".to_string(),
                 "apply_rules()".to_string(),
                 "measure_self_as_image(Static(\"url\"))".to_string(),
-            ]),
+            ], MockRect::default()),
             &MockLayoutNode(vec![
                 "apply_styles()".to_string(),
                 "apply_rules()".to_string(),
                 "append_child()".to_string(),
-            ]),
+            ], MockRect::default()),
             &MockLayoutNode(vec![
                 "apply_styles()".to_string(),
                 "apply_rules()".to_string(),
                 "measure_self_as_text(Static(\"Hello\"))".to_string(),
-            ]),
+            ], MockRect::default()),
             &MockLayoutNode(vec![
                 "apply_styles()".to_string(),
                 "apply_rules()".to_string(),
                 "measure_self_as_text(Static(\"world\"))".to_string(),
-            ]),
+            ], MockRect::default()),
             &MockLayoutNode(vec![
                 "apply_styles()".to_string(),
                 "apply_rules()".to_string(),
                 "measure_self_as_text(Static(\"!\"))".to_string(),
-            ]),
+            ], MockRect::default()),
         ],
     );
 }
+
+#[test]
+fn test_clone_node() {
+    let mut tree = fragment! {
+        DOMNode::from((
+            DOMTagName::from(KnownElementName::Div),
+            vec![
+                DOMAttribute::from((DOMAttributeName::from("foo"), DOMAttributeValue::from(true))),
+            ],
+            vec![
+                DOMNode::from("Hello"),
+                DOMNode::from({ "world" }),
+            ]
+        ))
+    };
+
+    tree.generate_layout_tree(&());
+
+    let shallow = tree.document().clone_node(false);
+    let deep = tree.document().clone_node(true);
+
+    assert_eq!(
+        shallow.document().descendants_iter().collect::<Vec<_>>(),
+        vec![
+            &DOMNode::from((
+                DOMTagName::from(KnownElementName::Div),
+                vec![
+                    DOMAttribute::from((DOMAttributeName::from("foo"), DOMAttributeValue::from(true))),
+                ]
+            )),
+        ]
+    );
+
+    assert_eq!(
+        deep.document().descendants_iter().collect::<Vec<_>>(),
+        vec![
+            &DOMNode::from((
+                DOMTagName::from(KnownElementName::Div),
+                vec![
+                    DOMAttribute::from((DOMAttributeName::from("foo"), DOMAttributeValue::from(true))),
+                ]
+            )),
+            &DOMNode::from("Hello"),
+            &DOMNode::from("world"),
+        ]
+    );
+
+    assert_eq!(
+        deep.document()
+            .descendants_iter()
+            .map(|v| v.layout_node())
+            .collect::<Vec<_>>(),
+        vec![
+            &MockLayoutNode(vec![
+                "apply_styles()".to_string(),
+                "apply_rules()".to_string(),
+            ], MockRect::default()),
+            &MockLayoutNode(vec![
+                "apply_styles()".to_string(),
+                "apply_rules()".to_string(),
+            ], MockRect::default()),
+            &MockLayoutNode(vec![
+                "apply_styles()".to_string(),
+                "apply_rules()".to_string(),
+            ], MockRect::default()),
+        ]
+    );
+}
+
+#[test]
+fn test_split_off() {
+    let mut tree = fragment! {
+        DOMNode::from((
+            DOMTagName::from(KnownElementName::Div),
+            vec![
+                DOMAttribute::from((DOMAttributeName::from("foo"), DOMAttributeValue::from(true))),
+            ],
+            vec![
+                DOMNode::from((
+                    DOMTagName::from("bar"),
+                    vec![],
+                    vec![DOMNode::from("Hello")]
+                )),
+                DOMNode::from({ "world" }),
+            ]
+        ))
+    };
+
+    tree.generate_layout_tree(&());
+
+    let bar_id = tree.document().first_child_id().unwrap();
+    let portal = tree.split_off(bar_id, &()).unwrap();
+    let root_id = tree.root().id();
+    assert!(tree.split_off(root_id, &()).is_none());
+    assert!(tree.split_off(bar_id, &()).is_none());
+
+    assert_eq!(
+        tree.document()
+            .descendants_iter()
+            .map(|v| v.is_text())
+            .collect::<Vec<_>>(),
+        vec![false, true]
+    );
+
+    assert_eq!(
+        portal.document()
+            .descendants_iter()
+            .map(|v| v.layout_node())
+            .collect::<Vec<_>>(),
+        vec![
+            &MockLayoutNode(vec![
+                "apply_styles()".to_string(),
+                "apply_rules()".to_string(),
+                "append_child()".to_string(),
+            ], MockRect::default()),
+            &MockLayoutNode(vec![
+                "apply_styles()".to_string(),
+                "apply_rules()".to_string(),
+                "measure_self_as_text(Static(\"Hello\"))".to_string(),
+            ], MockRect::default()),
+        ]
+    );
+
+    assert_eq!(
+        tree.document().layout_node(),
+        &MockLayoutNode(vec![
+            "apply_styles()".to_string(),
+            "apply_rules()".to_string(),
+            "append_child()".to_string(),
+            "append_child()".to_string(),
+            "remove_child()".to_string(),
+        ], MockRect::default())
+    );
+    assert_eq!(
+        portal.root().layout_node(),
+        &MockLayoutNode(vec![
+            "apply_styles()".to_string(),
+            "apply_rules()".to_string(),
+            "append_child()".to_string(),
+        ], MockRect::default())
+    );
+}
+
+#[test]
+fn test_unwrap_and_wrap() {
+    let mut tree = fragment! {
+        DOMNode::from((
+            DOMTagName::from(KnownElementName::Div),
+            vec![],
+            vec![
+                DOMNode::from("Hello"),
+                DOMNode::from((
+                    DOMTagName::from("bar"),
+                    vec![],
+                    vec![
+                        DOMNode::from("world"),
+                        DOMNode::from({ "!" }),
+                    ]
+                )),
+            ]
+        ))
+    };
+
+    tree.generate_layout_tree(&());
+
+    let bar_id = tree.document().last_child_id().unwrap();
+    tree.get_mut(bar_id).unwrap_node_with_layout(&()).unwrap();
+
+    assert_eq!(
+        tree.document()
+            .children_iter()
+            .map(|v| v.is_text())
+            .collect::<Vec<_>>(),
+        vec![true, true, true]
+    );
+
+    assert_eq!(
+        tree.document().layout_node(),
+        &MockLayoutNode(vec![
+            "apply_styles()".to_string(),
+            "apply_rules()".to_string(),
+            "append_child()".to_string(),
+            "append_child()".to_string(),
+            "remove_child()".to_string(),
+            "insert_child(1)".to_string(),
+            "insert_child(2)".to_string(),
+        ], MockRect::default())
+    );
+
+    // Promoted children are measured again against their new parent.
+    assert_eq!(
+        tree.document().children_iter().nth(1).unwrap().layout_node().0[2..].to_vec(),
+        vec![
+            "measure_self_as_text(Static(\"world\"))".to_string(),
+            "measure_self_as_text(Static(\"world\"))".to_string(),
+        ]
+    );
+
+    let hello_id = tree.document().first_child_id().unwrap();
+    let span_id = tree.get_mut(hello_id)
+        .wrap_with_layout(DOMNode::from((DOMTagName::from(KnownElementName::Span), vec![])), &())
+        .id();
+
+    assert_eq!(tree.document().first_child_id(), Some(span_id));
+    assert_eq!(tree.get(hello_id).parent_id(), Some(span_id));
+
+    assert_eq!(
+        tree.get(span_id).layout_node(),
+        &MockLayoutNode(vec![
+            "apply_styles()".to_string(),
+            "apply_rules()".to_string(),
+            "append_child()".to_string(),
+        ], MockRect::default())
+    );
+    assert_eq!(
+        tree.document().layout_node().0[7..].to_vec(),
+        vec!["remove_child()".to_string(), "insert_child(0)".to_string()]
+    );
+    assert_eq!(
+        tree.get(hello_id).layout_node().0[2..].to_vec(),
+        vec![
+            "measure_self_as_text(Static(\"Hello\"))".to_string(),
+            "measure_self_as_text(Static(\"Hello\"))".to_string(),
+        ]
+    );
+}
+
+#[test]
+fn test_replace_swap_and_move() {
+    let mut tree = fragment! {
+        DOMNode::from((
+            DOMTagName::from(KnownElementName::Div),
+            vec![],
+            vec![
+                DOMNode::from("Hello"),
+                DOMNode::from((
+                    DOMTagName::from("bar"),
+                    vec![],
+                    vec![DOMNode::from("world")]
+                )),
+                DOMNode::from({ "!" }),
+            ]
+        ))
+    };
+
+    tree.generate_layout_tree(&());
+
+    let document_id = tree.document().id();
+    let hello_id = tree.document().first_child_id().unwrap();
+    let bar_id = tree.get(hello_id).next_sibling_id().unwrap();
+    let world_id = tree.get(bar_id).first_child_id().unwrap();
+    let bang_id = tree.document().last_child_id().unwrap();
+
+    tree.swap_nodes_with_layout(hello_id, bang_id, &()).unwrap();
+    assert_eq!(tree.document().first_child_id(), Some(bang_id));
+    assert_eq!(tree.get(bar_id).child_index(), Some(1));
+    assert_eq!(tree.document().last_child_id(), Some(hello_id));
+    assert_eq!(
+        tree.document().layout_node().0[5..].to_vec(),
+        vec![
+            "remove_child()".to_string(),
+            "remove_child()".to_string(),
+            "insert_child(0)".to_string(),
+            "insert_child(2)".to_string(),
+        ]
+    );
+
+    tree.get_mut(world_id).move_to_with_layout(document_id, 0, &()).unwrap();
+    assert_eq!(tree.document().first_child_id(), Some(world_id));
+    assert_eq!(tree.get(bar_id).first_child_id(), None);
+    assert_eq!(
+        tree.get(bar_id).layout_node().0[2..].to_vec(),
+        vec!["append_child()".to_string(), "remove_child()".to_string()]
+    );
+    assert_eq!(tree.get_mut(bar_id).move_to_with_layout(bar_id, 0, &()), Err(()));
+
+    let old = tree.get_mut(hello_id).replace_with_layout(DOMNode::from("Goodbye"), &());
+    assert!(old.is_text());
+    assert_eq!(
+        tree.get(hello_id).layout_node(),
+        &MockLayoutNode(vec![
+            "apply_styles()".to_string(),
+            "apply_rules()".to_string(),
+            "measure_self_as_text(Static(\"Goodbye\"))".to_string(),
+        ], MockRect::default())
+    );
+
+    tree.get_mut(bar_id).replace_with_subtree_with_layout(hello_id, &()).unwrap();
+    assert_eq!(tree.document().first_child_id(), Some(world_id));
+    assert_eq!(tree.get(bang_id).child_index(), Some(1));
+    assert_eq!(tree.document().last_child_id(), Some(hello_id));
+    assert_eq!(tree.get(bar_id).parent_id(), None);
+
+    let old = tree.get_mut(document_id)
+        .replace_with_layout(DOMNode::from((DOMTagName::from(KnownElementName::Span), vec![])), &());
+    assert!(old.is_known(KnownElementName::Div));
+    assert_eq!(
+        tree.get(world_id).layout_node().0.last(),
+        Some(&"measure_self_as_text(Static(\"world\"))".to_string())
+    );
+    assert_eq!(tree.get(world_id).layout_node().0.len(), 5);
+}
+
+#[test]
+fn test_reorder_children() {
+    let mut tree = fragment! {
+        DOMNode::from((
+            DOMTagName::from(KnownElementName::Div),
+            vec![],
+            vec![
+                DOMNode::from("a"),
+                DOMNode::from({ "b" }),
+                DOMNode::from({ "c" }),
+                DOMNode::from({ "d" }),
+            ]
+        ))
+    };
+
+    tree.generate_layout_tree(&());
+
+    let a_id = tree.document().first_child_id().unwrap();
+    let b_id = tree.get(a_id).next_sibling_id().unwrap();
+    let c_id = tree.get(b_id).next_sibling_id().unwrap();
+    let d_id = tree.get(c_id).next_sibling_id().unwrap();
+
+    tree.document_mut().reorder_children_with_layout(&[b_id, c_id, a_id, d_id]).unwrap();
+    assert_eq!(tree.document().first_child_id(), Some(b_id));
+    assert_eq!(tree.get(a_id).child_index(), Some(2));
+    assert_eq!(
+        tree.document().layout_node().0[6..].to_vec(),
+        vec!["remove_child()".to_string(), "insert_child(2)".to_string()]
+    );
+
+    tree.document_mut().reverse_children_with_layout().unwrap();
+    assert_eq!(tree.document().first_child_id(), Some(d_id));
+    assert_eq!(tree.document().last_child_id(), Some(b_id));
+    assert_eq!(tree.document().layout_node().0.len(), 14);
+
+    tree.document_mut().sort_children_by_key_with_layout(|v| v.is_text()).unwrap();
+    assert_eq!(tree.document().first_child_id(), Some(d_id));
+    assert_eq!(tree.document().layout_node().0.len(), 14);
+
+    assert_eq!(tree.document_mut().reorder_children_with_layout(&[a_id]), Err(()));
+
+    // Siblings that aren't in the layout don't count towards layout indices.
+    let e_id = tree.document_mut().prepend(DOMNode::from("e")).id();
+    let span_id = tree.get_mut(c_id)
+        .wrap_with_layout(DOMNode::from(DOMTagName::from(KnownElementName::Span)), &())
+        .id();
+    assert_eq!(tree.get(span_id).child_index(), Some(3));
+    assert_eq!(
+        tree.document().layout_node().0[14..].to_vec(),
+        vec!["remove_child()".to_string(), "insert_child(2)".to_string()]
+    );
+
+    tree.document_mut().reorder_children_with_layout(&[span_id, e_id, d_id, a_id, b_id]).unwrap();
+    assert_eq!(
+        tree.document().layout_node().0[16..].to_vec(),
+        vec!["remove_child()".to_string(), "insert_child(0)".to_string()]
+    );
+}
+
+#[test]
+fn test_observe() {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    let mut tree = fragment! {
+        DOMNode::from((
+            DOMTagName::from(KnownElementName::Div),
+            vec![],
+            vec![
+                DOMNode::from("Hello"),
+                DOMNode::from({ "world" }),
+            ]
+        ))
+    };
+
+    let records = Rc::new(RefCell::new(vec![]));
+    {
+        let records = records.clone();
+        tree.observe(move |record| records.borrow_mut().push(format!("{:?}", record).split(' ').next().unwrap().to_string()));
+    }
+
+    let document_id = tree.document().id();
+    let hello_id = tree.document().first_child_id().unwrap();
+    let world_id = tree.document().last_child_id().unwrap();
+
+    tree.get_mut(world_id).move_to(document_id, 0);
+    {
+        let mut hello = tree.get_mut(hello_id);
+        let _: &mut DOMNode = &mut hello;
+    }
+    {
+        let mut document = tree.document_mut();
+        let _: &mut DOMNode = &mut document;
+    }
+
+    tree.get_mut(hello_id).set_text_content("Hi".to_string(), &());
+    tree.document_mut().apply_styles(());
+    assert_eq!(tree.get_mut(hello_id).apply_styles(()), None);
+
+    assert_eq!(*records.borrow(), vec!["ChildList", "ChildList", "CharacterData", "Attributes"]);
+}
+
+#[test]
+fn test_undo_with_layout() {
+    let mut tree = fragment! {
+        DOMNode::from((
+            DOMTagName::from(KnownElementName::Div),
+            vec![],
+            vec![
+                DOMNode::from("a"),
+                DOMNode::from({ "b" }),
+                DOMNode::from({ "c" }),
+            ]
+        ))
+    };
+
+    tree.generate_layout_tree(&());
+
+    let a_id = tree.document().first_child_id().unwrap();
+    let b_id = tree.get(a_id).next_sibling_id().unwrap();
+    let c_id = tree.get(b_id).next_sibling_id().unwrap();
+    let log_len = tree.document().layout_node().0.len();
+
+    tree.begin();
+    tree.document_mut()
+        .reorder_children_with_layout(&[c_id, a_id, b_id])
+        .unwrap();
+    assert!(tree.commit());
+    assert_eq!(
+        tree.document().layout_node().0[log_len..].to_vec(),
+        vec!["remove_child()".to_string(), "insert_child(0)".to_string()]
+    );
+
+    // The document's value was handed out during the transaction, so putting it back
+    // refreshes its styles as well.
+    let detach_and_attach = vec![
+        "remove_child()",
+        "remove_child()",
+        "remove_child()",
+        "reset_custom_styles()",
+        "apply_styles()",
+        "apply_rules()",
+        "mark_dirty()",
+        "append_child()",
+        "append_child()",
+        "append_child()",
+    ];
+
+    assert!(tree.undo(&()));
+    assert_eq!(tree.document().first_child_id(), Some(a_id));
+    assert_eq!(tree.document().last_child_id(), Some(c_id));
+    assert_eq!(
+        tree.document().layout_node().0[log_len + 2..].to_vec(),
+        detach_and_attach
+    );
+
+    assert!(tree.redo(&()));
+    assert_eq!(tree.document().first_child_id(), Some(c_id));
+    assert_eq!(
+        tree.document().layout_node().0[log_len + 12..].to_vec(),
+        detach_and_attach
+    );
+
+    tree.begin();
+    tree.document_mut().remove_with_layout(a_id).unwrap();
+    assert_eq!(tree.get(a_id).parent_id(), None);
+    assert!(tree.rollback(&()));
+    assert_eq!(tree.get(a_id).parent_id(), Some(tree.document().id()));
+    assert_eq!(tree.get(a_id).child_index(), Some(1));
+    assert_eq!(
+        tree.document().layout_node().0[log_len + 22..].to_vec(),
+        vec![
+            "remove_child()",
+            "remove_child()",
+            "remove_child()",
+            "reset_custom_styles()",
+            "apply_styles()",
+            "apply_rules()",
+            "mark_dirty()",
+            "append_child()",
+            "append_child()",
+            "append_child()"
+        ]
+    );
+
+    // Nodes that leave the tree are put back, and measured again.
+    tree.begin();
+    let portal = tree.split_off(a_id, &()).unwrap();
+    assert!(tree.commit());
+    assert!(portal.document().is_text());
+    let log_len = tree.document().layout_node().0.len();
+    assert!(tree.undo(&()));
+    assert_eq!(tree.get(a_id).child_index(), Some(1));
+    assert_eq!(
+        tree.document().layout_node().0[log_len..].to_vec(),
+        vec![
+            "remove_child()",
+            "remove_child()",
+            "reset_custom_styles()",
+            "apply_styles()",
+            "apply_rules()",
+            "mark_dirty()",
+            "append_child()",
+            "append_child()",
+            "append_child()"
+        ]
+    );
+    assert_eq!(
+        tree.get(a_id).layout_node().0[2..].to_vec(),
+        vec![
+            "reset_custom_styles()",
+            "apply_styles()",
+            "apply_rules()",
+            "mark_dirty()",
+            "measure_self_as_text(Static(\"a\"))",
+        ]
+    );
+
+    let wrapper_id = tree.get_mut(b_id)
+        .wrap_with_layout(DOMNode::from(DOMTagName::from(KnownElementName::Span)), &())
+        .id();
+    tree.begin();
+    tree.get_mut(wrapper_id).unwrap_node_with_layout(&()).unwrap();
+    assert!(tree.commit());
+    let log_len = tree.document().layout_node().0.len();
+    assert!(tree.undo(&()));
+    assert_eq!(tree.get(b_id).parent_id(), Some(wrapper_id));
+    assert_eq!(tree.get(wrapper_id).child_index(), Some(2));
+    assert_eq!(
+        tree.document().layout_node().0[log_len..].to_vec(),
+        detach_and_attach
+    );
+    assert_eq!(
+        tree.get(wrapper_id).layout_node().0,
+        vec![
+            "apply_styles()",
+            "apply_rules()",
+            "reset_custom_styles()",
+            "apply_styles()",
+            "apply_rules()",
+            "mark_dirty()",
+            "append_child()",
+        ]
+    );
+}
+
+#[test]
+fn test_node_path() {
+    let tree = fragment! {
+        DOMNode::from((
+            DOMTagName::from(KnownElementName::Div),
+            vec![],
+            vec![
+                DOMNode::from("Hello"),
+                DOMNode::from({ "world" }),
+            ]
+        ))
+    };
+
+    let world_id = tree.document().last_child_id().unwrap();
+    let path = tree.get(world_id).path().unwrap();
+    assert_eq!(path.to_string(), "/0/1");
+    assert_eq!(tree.resolve(&"/0/1".parse().unwrap()), Some(world_id));
+    assert_eq!(tree.resolve(&NodePath::root()), Some(tree.root().id()));
+}
+
+#[test]
+fn test_subtree_hash() {
+    let mut tree = fragment! {
+        DOMNode::from((
+            DOMTagName::from(KnownElementName::Div),
+            vec![],
+            vec![
+                DOMNode::from((DOMTagName::from(KnownElementName::Span), vec![], vec![DOMNode::from("Hello")])),
+                DOMNode::from((
+                    DOMTagName::from(KnownElementName::Span),
+                    vec![
+                        DOMAttribute::from((DOMAttributeName::from(EventType::Click), DOMAttributeValue::from(Closure::new(|_| {})))),
+                    ],
+                    vec![DOMNode::from("Hello")]
+                )),
+                DOMNode::from((DOMTagName::from(KnownElementName::Span), vec![], vec![DOMNode::from("world")])),
+            ]
+        ))
+    };
+
+    let first_id = tree.document().first_child_id().unwrap();
+    let second_id = tree.get(first_id).next_sibling_id().unwrap();
+    let third_id = tree.document().last_child_id().unwrap();
+
+    let hash = tree.get(second_id).subtree_hash();
+    assert_eq!(tree.get(first_id).subtree_hash(), hash);
+    assert_ne!(tree.get(third_id).subtree_hash(), hash);
+    assert_eq!(tree.find_subtree_by_hash(hash), Some(first_id));
+
+    tree.enable_subtree_hashes();
+    let tree_hash = tree.subtree_hash();
+    assert_eq!(tree_hash, tree.clone_tree().subtree_hash());
+
+    tree.get_mut(first_id).detach();
+    assert_ne!(tree.subtree_hash(), tree_hash);
+    assert_eq!(tree.find_subtree_by_hash(hash), Some(second_id));
+}
+
+#[test]
+fn test_forest() {
+    let tree = fragment! {
+        DOMNode::from("Hello")
+        DOMNode::from((
+            DOMTagName::from(KnownElementName::Div),
+            vec![],
+            vec![
+                DOMNode::from("world"),
+            ]
+        ))
+    };
+
+    let mut forest = tree.into_forest();
+    assert_eq!(forest.len(), 2);
+    assert_eq!(forest.outer_html(SerializeOptions::minified()), "Hello<div>world</div>");
+
+    let hello_id = forest.roots_ids_iter().next().unwrap();
+    assert!(forest.is_root(hello_id));
+    assert_eq!(forest.get(hello_id).parent_id(), None);
+
+    let hello = forest.remove_root(hello_id).unwrap();
+    assert_eq!(*hello.document(), DOMNode::from("Hello"));
+
+    let ids = forest.push_tree(fragment! {
+        DOMNode::from("!")
+    });
+    forest.insert_node(0, DOMNode::from("Hi"));
+    assert_eq!(ids.len(), 1);
+    assert_eq!(forest.outer_html(SerializeOptions::minified()), "Hi<div>world</div>!");
+
+    let tree = forest.into_tree();
+    assert_eq!(tree.root().children_iter().count(), 3);
+    assert_eq!(tree.root().inner_html(SerializeOptions::minified()), "Hi<div>world</div>!");
+}
+
+#[test]
+fn test_filter_descendants() {
+    let mut tree = fragment! {
+        DOMNode::from((
+            DOMTagName::from(KnownElementName::Div),
+            vec![],
+            vec![
+                DOMNode::from("Hello"),
+                DOMNode::from((DOMTagName::from(KnownElementName::Span), vec![], vec![DOMNode::from("world")])),
+            ]
+        ))
+    };
+
+    let removed = tree.root_mut().filter_descendants(|node| match node.is_text() {
+        true => FilterAction::Keep,
+        false if node.children_iter().count() == 1 => FilterAction::Unwrap,
+        false => FilterAction::Keep
+    });
+    assert_eq!(removed, 1);
+    assert_eq!(tree.document().children_iter().count(), 2);
+
+    let removed = tree.root_mut().retain_descendants(|node| !node.is_text());
+    assert_eq!(removed, 2);
+    assert_eq!(tree.document().children_iter().count(), 0);
+}
+
+#[test]
+fn test_filter_descendants_with_layout() {
+    let mut tree = fragment! {
+        DOMNode::from((
+            DOMTagName::from(KnownElementName::Div),
+            vec![],
+            vec![
+                DOMNode::from("Hello"),
+                DOMNode::from((
+                    DOMTagName::from("bar"),
+                    vec![],
+                    vec![
+                        DOMNode::from("world"),
+                        DOMNode::from((DOMTagName::from(KnownElementName::Span), vec![], vec![DOMNode::from("!")])),
+                    ]
+                )),
+            ]
+        ))
+    };
+
+    tree.generate_layout_tree(&());
+    let log_len = tree.document().layout_node().0.len();
+
+    let removed = tree.document_mut().filter_descendants_with_layout(
+        |node| match node.is_text() {
+            true => FilterAction::Keep,
+            false if node.children_iter().count() == 2 => FilterAction::Unwrap,
+            false => FilterAction::Keep
+        },
+        &()
+    );
+    assert_eq!(removed, 1);
+    assert_eq!(tree.document().children_iter().count(), 3);
+    assert_eq!(
+        tree.document().layout_node().0[log_len..].to_vec(),
+        vec!["remove_child()", "insert_child(1)", "insert_child(2)"]
+    );
+
+    // Promoted children are measured again against their new parent.
+    let world_id = tree.document().children_refs().nth(1).unwrap().id();
+    assert_eq!(
+        tree.get(world_id).layout_node().0[2..].to_vec(),
+        vec!["measure_self_as_text(Static(\"world\"))", "measure_self_as_text(Static(\"world\"))"]
+    );
+
+    let removed = tree.document_mut().retain_descendants_with_layout(|node| node.is_text() || node.children_iter().count() == 0, &());
+    assert_eq!(removed, 2);
+    assert_eq!(tree.document().children_iter().count(), 2);
+    assert_eq!(
+        tree.document().layout_node().0[log_len + 3..].to_vec(),
+        vec!["remove_child()"]
+    );
+}
+
+#[test]
+fn test_collect_garbage() {
+    let mut tree = fragment! {
+        DOMNode::from((
+            DOMTagName::from(KnownElementName::Div),
+            vec![],
+            vec![
+                DOMNode::from("Hello"),
+                DOMNode::from((DOMTagName::from(KnownElementName::Span), vec![], vec![DOMNode::from("world")])),
+            ]
+        ))
+    };
+
+    let span_id = tree.document().last_child_id().unwrap();
+    tree.get_mut(span_id).detach();
+    tree.alloc(DOMNode::from("orphan"));
+    assert_eq!(tree.orphans().len(), 3);
+    assert_eq!(tree.collect_garbage(), 3);
+    assert!(tree.orphans().is_empty());
+    assert_eq!(tree.document().children_iter().count(), 1);
+}
+
+#[test]
+fn test_range_with_layout() {
+    let mut tree = fragment! {
+        DOMNode::from((
+            DOMTagName::from(KnownElementName::Div),
+            vec![],
+            vec![
+                DOMNode::from("a"),
+                DOMNode::from({ "b" }),
+                DOMNode::from({ "c" }),
+                DOMNode::from({ "d" }),
+            ]
+        ))
+    };
+
+    tree.generate_layout_tree(&());
+
+    let a_id = tree.document().first_child_id().unwrap();
+    let b_id = tree.get(a_id).next_sibling_id().unwrap();
+    let d_id = tree.document().last_child_id().unwrap();
+    let log_len = tree.document().layout_node().0.len();
+
+    tree.document_mut()
+        .move_range_after_with_layout(a_id, b_id, d_id, &())
+        .unwrap();
+    assert_eq!(tree.document().first_child().unwrap().next_sibling_id(), Some(d_id));
+    assert_eq!(
+        tree.document().layout_node().0[log_len..].to_vec(),
+        vec!["remove_child()", "remove_child()", "insert_child(2)", "insert_child(3)"]
+    );
+
+    let ids = tree.document_mut().detach_range_with_layout(d_id, a_id).unwrap();
+    assert_eq!(ids, vec![d_id, a_id]);
+    assert_eq!(tree.document().children_iter().count(), 2);
+    assert_eq!(
+        tree.document().layout_node().0[log_len + 4..].to_vec(),
+        vec!["remove_child()", "remove_child()"]
+    );
+}
+
+#[test]
+fn test_element_from_point() {
+    let mut tree = fragment! {
+        DOMNode::from((
+            DOMTagName::from(KnownElementName::Div),
+            vec![],
+            vec![
+                DOMNode::from((
+                    DOMTagName::from(KnownElementName::Span),
+                    vec![],
+                    vec![DOMNode::from((DOMTagName::from(KnownElementName::Span), vec![]))]
+                )),
+                DOMNode::from("a"),
+            ]
+        ))
+    };
+
+    tree.generate_layout_tree(&());
+
+    let root_id = tree.root().id();
+    let document_id = tree.document().id();
+    let outer_id = tree.document().first_child_id().unwrap();
+    let inner_id = tree.get(outer_id).first_child_id().unwrap();
+    let text_id = tree.document().last_child_id().unwrap();
+
+    tree.get_mut(root_id).set_computed_client_position(MockRect(0.0, 0.0, 200.0, 200.0));
+    tree.get_mut(document_id).set_computed_client_position(MockRect(0.0, 0.0, 100.0, 100.0));
+    tree.get_mut(outer_id).set_computed_client_position(MockRect(0.0, 0.0, 50.0, 50.0));
+    tree.get_mut(inner_id).set_computed_client_position(MockRect(10.0, 10.0, 10.0, 10.0));
+    tree.get_mut(text_id).set_computed_client_position(MockRect(0.0, 0.0, 100.0, 100.0));
+
+    assert_eq!(tree.elements_from_point(15.0, 15.0), vec![inner_id, outer_id, document_id]);
+    assert_eq!(tree.element_from_point(15.0, 15.0), Some(inner_id));
+    assert_eq!(tree.elements_from_point(5.0, 5.0), vec![outer_id, document_id]);
+    assert_eq!(tree.element_from_point(75.0, 75.0), Some(document_id));
+    assert_eq!(tree.element_from_point(150.0, 5.0), None);
+}
+
+#[test]
+fn test_ref_iterators() {
+    let tree = fragment! {
+        DOMNode::from((
+            DOMTagName::from(KnownElementName::Div),
+            vec![],
+            vec![
+                DOMNode::from("Hello"),
+                DOMNode::from((DOMTagName::from(KnownElementName::Span), vec![], vec![DOMNode::from("world")])),
+            ]
+        ))
+    };
+
+    let document = tree.document();
+    let hello_id = document.first_child_id().unwrap();
+    let span_id = document.last_child_id().unwrap();
+    let world_id = tree.get(span_id).first_child_id().unwrap();
+
+    let children: Vec<_> = document.children_refs().map(|node| node.id()).collect();
+    assert_eq!(children, vec![hello_id, span_id]);
+
+    let descendants: Vec<_> = document.descendants_refs().map(|node| node.id()).collect();
+    assert_eq!(descendants, vec![document.id(), hello_id, span_id, world_id]);
+
+    let ancestors: Vec<_> = tree.get(world_id).ancestors_refs().map(|node| node.id()).collect();
+    assert_eq!(ancestors, vec![span_id, document.id(), tree.root().id()]);
+
+    let edges: Vec<_> = tree.get(span_id)
+        .traverse_edges()
+        .map(|edge| match edge {
+            DOMEdge::Open(node) => (true, node.id()),
+            DOMEdge::Close(node) => (false, node.id())
+        })
+        .collect();
+    assert_eq!(edges, vec![(true, span_id), (true, world_id), (false, world_id), (false, span_id)]);
+    assert_eq!(tree.get(span_id).traverse_edges().last().unwrap().node().id(), span_id);
+}
+
+#[test]
+fn test_dispatch_event() {
+    let log = Rc::new(RefCell::new(vec![]));
+    let listener = |name: &'static str| {
+        let log = Rc::clone(&log);
+        DOMAttributeValue::from(Closure::new(move |_| log.borrow_mut().push(name)))
+    };
+
+    let mut tree = fragment! {
+        DOMNode::from((
+            DOMTagName::from(KnownElementName::Div),
+            vec![
+                DOMAttribute::from((DOMAttributeName::from(EventType::Click), listener("div"))),
+            ],
+            vec![
+                DOMNode::from((
+                    DOMTagName::from(KnownElementName::Span),
+                    vec![
+                        DOMAttribute::from((DOMAttributeName::from(EventType::Click), listener("span"))),
+                        DOMAttribute::from((DOMAttributeName::from(EventType::MouseDown), listener("down"))),
+                    ],
+                    vec![]
+                )),
+            ]
+        ))
+    };
+
+    let div_id = tree.document().id();
+    let span_id = tree.document().first_child_id().unwrap();
+
+    let result = tree.dispatch_event(span_id, EventType::Click, ());
+    assert_eq!(*log.borrow(), vec!["span", "div"]);
+    assert_eq!(result.invoked, vec![(span_id, EventPhase::Target), (div_id, EventPhase::Bubble)]);
+    assert!(!result.propagation_stopped);
+    assert!(!result.default_prevented);
+
+    let result = tree.dispatch_event(div_id, EventType::MouseDown, ());
+    assert!(result.invoked.is_empty());
+}
+
+#[derive(Debug, Clone, Default)]
+struct StoppableEvent(Rc<Cell<(bool, bool)>>);
+
+impl TGenericEvent for StoppableEvent {}
+
+impl TDispatchEvent for StoppableEvent {
+    fn is_propagation_stopped(&self) -> bool {
+        (self.0).get().0
+    }
+
+    fn is_default_prevented(&self) -> bool {
+        (self.0).get().1
+    }
+
+    fn set_default_prevented(&self, prevented: bool) {
+        let (stopped, _) = (self.0).get();
+        (self.0).set((stopped, prevented));
+    }
+}
+
+#[test]
+fn test_dispatch_event_stop_propagation() {
+    type DOMNode = rsx_dom::types::DOMNode<StoppableEvent, (), MockComputedStyles, MockLayoutNode>;
+
+    let stop = DOMAttributeValue::from(Closure::new(|event: StoppableEvent| (event.0).set((true, true))));
+    let never = DOMAttributeValue::from(Closure::new(|_| panic!("Propagation wasn't stopped")));
+
+    let mut tree = DOMTree::default();
+    let span_id = {
+        let mut root = tree.root_mut();
+        let mut div = root.append(DOMNode::from((
+            DOMTagName::from(KnownElementName::Div),
+            vec![DOMAttribute::from((DOMAttributeName::from(EventType::Click), never))]
+        )));
+        div.append(DOMNode::from((
+            DOMTagName::from(KnownElementName::Span),
+            vec![DOMAttribute::from((DOMAttributeName::from(EventType::Click), stop))]
+        ))).id()
+    };
+
+    let result = tree.dispatch_event(span_id, EventType::Click, StoppableEvent::default());
+    assert_eq!(result.invoked, vec![(span_id, EventPhase::Target)]);
+    assert!(result.propagation_stopped);
+    assert!(result.default_prevented);
+}
+
+#[test]
+fn test_event_listeners() {
+    type DOMNode = rsx_dom::types::DOMNode<StoppableEvent, (), MockComputedStyles, MockLayoutNode>;
+
+    let log = Rc::new(RefCell::new(vec![]));
+    let listener = |name: &'static str, prevent: bool| {
+        let log = Rc::clone(&log);
+        Closure::new(move |event: StoppableEvent| {
+            log.borrow_mut().push(name);
+            if prevent {
+                (event.0).set((false, true));
+            }
+        })
+    };
+
+    let mut tree = DOMTree::default();
+    let (div_id, span_id) = {
+        let mut root = tree.root_mut();
+        let mut div = root.append(DOMNode::from((
+            DOMTagName::from(KnownElementName::Div),
+            vec![DOMAttribute::from((DOMAttributeName::from(EventType::Click), DOMAttributeValue::from(listener("div attribute", false))))]
+        )));
+        let span_id = div.append(DOMNode::from(DOMTagName::from(KnownElementName::Span))).id();
+        (div.id(), span_id)
+    };
+
+    let capture = ListenerOptions { capture: true, ..ListenerOptions::default() };
+    let once = ListenerOptions { once: true, ..ListenerOptions::default() };
+    let passive = ListenerOptions { passive: true, ..ListenerOptions::default() };
+
+    let bubble = tree.get_mut(div_id).add_event_listener(EventType::Click, listener("div bubble", false), ListenerOptions::default());
+    tree.get_mut(div_id).add_event_listener(EventType::Click, listener("div capture", false), capture);
+    tree.get_mut(span_id).add_event_listener(EventType::Click, listener("span once", false), once);
+    tree.get_mut(span_id).add_event_listener(EventType::Click, listener("span passive", true), passive);
+    tree.get_mut(span_id).add_event_listener(EventType::MouseDown, listener("span down", false), ListenerOptions::default());
+
+    assert_eq!(tree.get(span_id).listeners_for(EventType::Click).count(), 2);
+    assert_eq!(tree.get(div_id).listeners_for(EventType::Click).filter(|l| l.options().capture).count(), 1);
+
+    let result = tree.dispatch_event(span_id, EventType::Click, StoppableEvent::default());
+    assert_eq!(*log.borrow(), vec!["div capture", "span once", "span passive", "div attribute", "div bubble"]);
+    assert_eq!(
+        result.invoked,
+        vec![
+            (div_id, EventPhase::Capture),
+            (span_id, EventPhase::Target),
+            (span_id, EventPhase::Target),
+            (div_id, EventPhase::Bubble),
+            (div_id, EventPhase::Bubble),
+        ]
+    );
+    assert!(!result.default_prevented);
+    assert_eq!(tree.get(span_id).listeners_for(EventType::Click).count(), 1);
+
+    log.borrow_mut().clear();
+    let removed = tree.get_mut(div_id).remove_event_listener(bubble).unwrap();
+    assert_eq!(removed.handle(), bubble);
+    assert!(tree.get_mut(div_id).remove_event_listener(bubble).is_none());
+    assert!(tree.get_mut(span_id).remove_event_listener(bubble).is_none());
+
+    tree.dispatch_event(span_id, EventType::Click, StoppableEvent::default());
+    assert_eq!(*log.borrow(), vec!["div capture", "span passive", "div attribute"]);
+
+    // A passive listener calling preventDefault first doesn't hide a later one that counts.
+    let prevent = tree.get_mut(div_id).add_event_listener(EventType::Click, listener("div prevent", true), ListenerOptions::default());
+    let result = tree.dispatch_event(span_id, EventType::Click, StoppableEvent::default());
+    assert!(result.default_prevented);
+    tree.get_mut(div_id).remove_event_listener(prevent).unwrap();
+
+    // Clones of a node get listeners with handles of their own.
+    let clone = tree.get(span_id).clone_node(false);
+    let handles: Vec<_> = tree.get(span_id).listeners_for(EventType::Click).map(|l| l.handle()).collect();
+    let clone_handles: Vec<_> = clone.document().listeners_for(EventType::Click).map(|l| l.handle()).collect();
+    assert_eq!(clone_handles.len(), handles.len());
+    assert!(clone_handles.iter().all(|handle| !handles.contains(handle)));
+
+    log.borrow_mut().clear();
+    tree.get_mut(div_id).remove_all_listeners();
+    tree.get_mut(span_id).remove_all_listeners();
+    let result = tree.dispatch_event(span_id, EventType::Click, StoppableEvent::default());
+    assert!(result.invoked.is_empty());
+    assert!(log.borrow().is_empty());
+}
+
+#[test]
+fn test_serialize() {
+    let mut shadow = DOMTree::default();
+    shadow.root_mut().append(DOMNode::from((DOMTagName::from("slot"), vec![])));
+
+    let mut tree = DOMTree::default();
+    {
+        let mut root = tree.root_mut();
+        let mut div = root.append(DOMNode::from((
+            DOMTagName::from(KnownElementName::Div),
+            vec![
+                DOMAttribute::from((DOMAttributeName::from("title"), DOMAttributeValue::from("\"a\" & <b>"))),
+                DOMAttribute::from((DOMAttributeName::from("hidden"), DOMAttributeValue::from(true))),
+                DOMAttribute::from((DOMAttributeName::from("checked"), DOMAttributeValue::from(false))),
+                DOMAttribute::from((DOMAttributeName::from("width"), DOMAttributeValue::from(1.5))),
+                DOMAttribute::from((DOMAttributeName::from(EventType::Click), DOMAttributeValue::from(Closure::new(|_| {})))),
+            ]
+        )));
+        div.append(DOMNode::from("1 < 2 & 3"));
+        div.append(DOMNode::from((DOMTagName::from("br"), vec![])));
+        div.append(DOMNode::from((DOMTagName::from(KnownElementName::Span), vec![])));
+        div.append(DOMNode::from(shadow));
+    }
+
+    let div = tree.document();
+    assert_eq!(
+        div.outer_html(SerializeOptions::minified()),
+        "<div title=\"&quot;a&quot; &amp; &lt;b&gt;\" hidden width=\"1.5\">1 &lt; 2 &amp; 3<br><span></span><slot></slot></div>"
+    );
+    assert_eq!(div.inner_html(SerializeOptions::minified()), "1 &lt; 2 &amp; 3<br><span></span><slot></slot>");
+    assert_eq!(tree.root().outer_html(SerializeOptions::minified()), div.outer_html(SerializeOptions::minified()));
+    assert_eq!(
+        div.outer_html(SerializeOptions::pretty()),
+        "<div title=\"&quot;a&quot; &amp; &lt;b&gt;\" hidden width=\"1.5\">\n  1 &lt; 2 &amp; 3\n  <br>\n  <span></span>\n  <slot></slot>\n</div>"
+    );
+
+    let options = SerializeOptions {
+        syntax: MarkupSyntax::Xml,
+        shadow_hosts: ShadowHostMode::Emit,
+        indent: None
+    };
+    assert_eq!(
+        div.inner_html(options),
+        "1 &lt; 2 &amp; 3<br/><span/><template shadowrootmode=\"open\"><slot/></template>"
+    );
+
+    let mut bytes = vec![];
+    div.write_inner_html_io(&mut bytes, SerializeOptions::minified()).unwrap();
+    assert_eq!(String::from_utf8(bytes).unwrap(), div.inner_html(SerializeOptions::minified()));
+
+    // Known names are written from their markup names, and styles from their declarations.
+    let mut tree = DOMTree::default();
+    tree.root_mut().append(DOMNode::from((
+        DOMTagName::from(KnownElementName::Img),
+        vec![
+            DOMAttribute::from((DOMAttributeName::from(KnownAttributeName::Class), DOMAttributeValue::from("photo"))),
+            DOMAttribute::from((DOMAttributeName::from(KnownAttributeName::Style), DOMAttributeValue::Styles(()))),
+            DOMAttribute::from((DOMAttributeName::from(KnownAttributeName::Width), DOMAttributeValue::from(2.0))),
+        ]
+    )));
+    assert_eq!(tree.document().outer_html(SerializeOptions::minified()), "<img class=\"photo\" style=\"\" width=\"2\">");
+}
+
+#[cfg(feature = "rsx-parse")]
+#[test]
+fn test_from_rsx_str() {
+    let tree = DOMTree::<(), (), MockComputedStyles, MockLayoutNode>::from_rsx_str(
+        "<div id=\"bar\" src width={42} href=<span/>><span>hello</span><img:src/><style/></div>"
+    ).unwrap();
+
+    let div = tree.document();
+    assert!(div.is_known(KnownElementName::Div));
+    assert_eq!(
+        div.outer_html(SerializeOptions::minified()),
+        "<div id=\"bar\" src width=\"42\" href=\"&lt;span&gt;&lt;/span&gt;\"><span>hello</span><img:src></img:src><style></style></div>"
+    );
+
+    let tree = DOMTree::<(), (), MockComputedStyles, MockLayoutNode>::from_rsx_str("<button class=\"a\" href=\"b\"/>").unwrap();
+    assert_eq!(
+        *tree.document(),
+        DOMNode::from((
+            DOMTagName::from(KnownElementName::Button),
+            vec![
+                DOMAttribute::from((DOMAttributeName::from(KnownAttributeName::Class), DOMAttributeValue::from("a"))),
+                DOMAttribute::from((DOMAttributeName::from(KnownAttributeName::Href), DOMAttributeValue::from("b"))),
+            ]
+        ))
+    );
+
+    let result = DOMTree::<(), (), MockComputedStyles, MockLayoutNode>::from_rsx_str("<div><foo.bar/></div>");
+    assert_eq!(result.unwrap_err(), DOMConvertError::MemberExpression("foo.bar".to_string()));
+
+    let result = DOMTree::<(), (), MockComputedStyles, MockLayoutNode>::from_rsx_str("<div>{value}</div>");
+    assert_eq!(result.unwrap_err(), DOMConvertError::CodeBlock);
+
+    let result = DOMTree::<(), (), MockComputedStyles, MockLayoutNode>::from_rsx_str("<div><custom-element/></div>");
+    assert_eq!(result.unwrap_err(), DOMConvertError::UnknownName("custom-element".to_string()));
+
+    let result = DOMTree::<(), (), MockComputedStyles, MockLayoutNode>::from_rsx_str("<div foo:id=\"bar\"/>");
+    assert_eq!(result.unwrap_err(), DOMConvertError::UnknownName("foo".to_string()));
+
+    let result = DOMTree::<(), (), MockComputedStyles, MockLayoutNode>::from_rsx_str("<div {...props}/>");
+    assert_eq!(result.unwrap_err(), DOMConvertError::SpreadAttribute);
+
+    match DOMTree::<(), (), MockComputedStyles, MockLayoutNode>::from_rsx_str("div") {
+        Err(DOMConvertError::Parse(_)) => {}
+        result => panic!("Expected a parse error, got {:?}", result)
+    }
+}
@@ -0,0 +1,236 @@
+/*
+Copyright 2016 Mozilla
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+this file except in compliance with the License. You may obtain a copy of the
+License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed
+under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+CONDITIONS OF ANY KIND, either express or implied. See the License for the
+specific language governing permissions and limitations under the License.
+*/
+
+use std::any::Any;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+
+use types::{Id, MutationRecord, Ref, SubtreeHash, Tree};
+
+// The aggregate of a node is its own value combined with the aggregates of all its
+// children, folding from the left: `from_value(v).combine(&a0).combine(&a1)`. The
+// grouping never changes, so `combine` doesn't need to be associative.
+pub trait Aggregate<T> {
+    fn from_value(value: &T) -> Self;
+    fn combine(&self, other: &Self) -> Self;
+}
+
+pub(crate) type AggregateMap<T, A> = RefCell<HashMap<Id<T>, A>>;
+
+trait Cache<T> {
+    fn remove(&self, id: Id<T>) -> bool;
+    fn clear(&self);
+    fn as_any(&self) -> &Any;
+}
+
+impl<T, A> Cache<T> for AggregateMap<T, A>
+where
+    T: 'static,
+    A: 'static
+{
+    fn remove(&self, id: Id<T>) -> bool {
+        self.borrow_mut().remove(&id).is_some()
+    }
+
+    fn clear(&self) {
+        self.borrow_mut().clear();
+    }
+
+    fn as_any(&self) -> &Any {
+        self
+    }
+}
+
+// Aggregates are computed lazily. Whenever a node is cached all of its descendants
+// are too, so invalidating can stop at the first ancestor that isn't.
+pub(crate) struct Aggregates<T> {
+    cache: Option<Box<Cache<T>>>,
+    pub(crate) hashes: Option<AggregateMap<T, SubtreeHash>>
+}
+
+impl<T> Default for Aggregates<T> {
+    fn default() -> Self {
+        Aggregates {
+            cache: None,
+            hashes: None
+        }
+    }
+}
+
+impl<T> fmt::Debug for Aggregates<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            f,
+            "Aggregates({}, {})",
+            self.cache.is_some(),
+            self.hashes.is_some()
+        )
+    }
+}
+
+// Caches are derived data, and never make two trees different.
+impl<T> PartialEq for Aggregates<T> {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl<T> Aggregates<T> {
+    pub(crate) fn invalidate(&self, record: &MutationRecord<T>, tree: &Tree<T>) {
+        use self::MutationRecord::*;
+
+        if self.cache.is_none() && self.hashes.is_none() {
+            return;
+        }
+
+        let id = match *record {
+            Append { parent_id, .. } | Prepend { parent_id, .. } | Detach { parent_id, .. } | Reorder { parent_id } => Some(parent_id),
+            InsertBefore { sibling_id, .. } | InsertAfter { sibling_id, .. } => tree.get(sibling_id).parent_id(),
+            ValueMut { id } => Some(id),
+            Remove { id } => {
+                // Removed nodes are already detached, so nothing else depends on them.
+                self.remove(id);
+                None
+            }
+        };
+
+        if let Some(ref cache) = self.cache {
+            invalidate_path(tree, id, |id| cache.remove(id));
+        }
+        if let Some(ref hashes) = self.hashes {
+            invalidate_path(tree, id, |id| hashes.borrow_mut().remove(&id).is_some());
+        }
+    }
+
+    pub(crate) fn clear(&self) {
+        if let Some(ref cache) = self.cache {
+            cache.clear();
+        }
+        if let Some(ref hashes) = self.hashes {
+            hashes.borrow_mut().clear();
+        }
+    }
+
+    fn remove(&self, id: Id<T>) {
+        if let Some(ref cache) = self.cache {
+            cache.remove(id);
+        }
+        if let Some(ref hashes) = self.hashes {
+            hashes.borrow_mut().remove(&id);
+        }
+    }
+
+    fn get<A>(&self) -> Option<&AggregateMap<T, A>>
+    where
+        T: 'static,
+        A: 'static
+    {
+        self.cache.as_ref()?.as_any().downcast_ref()
+    }
+}
+
+impl<T> Tree<T> {
+    // Values handed out without a mutation record still change the aggregates.
+    pub(crate) fn invalidate_aggregate(&self, id: Id<T>) {
+        self.aggregates
+            .invalidate(&MutationRecord::ValueMut { id }, self);
+    }
+}
+
+impl<T> Tree<T>
+where
+    T: 'static
+{
+    // Only one kind of aggregate is cached at a time, enabling another one replaces it.
+    pub fn enable_aggregates<A>(&mut self)
+    where
+        A: Aggregate<T> + Clone + 'static
+    {
+        let cache: AggregateMap<T, A> = RefCell::new(HashMap::new());
+        self.aggregates.cache = Some(Box::new(cache));
+    }
+
+    pub fn disable_aggregates(&mut self) {
+        self.aggregates.cache = None;
+    }
+}
+
+impl<'a, T> Ref<'a, T>
+where
+    T: 'static
+{
+    pub fn aggregate<A>(&self) -> Option<A>
+    where
+        A: Aggregate<T> + Clone + 'static
+    {
+        let cache = self.tree().aggregates.get::<A>()?;
+        compute(*self, &mut cache.borrow_mut())
+    }
+}
+
+fn invalidate_path<T, F>(tree: &Tree<T>, mut id: Option<Id<T>>, mut remove: F)
+where
+    F: FnMut(Id<T>) -> bool
+{
+    while let Some(ancestor_id) = id {
+        if !remove(ancestor_id) {
+            break;
+        }
+        id = tree.get(ancestor_id).parent_id();
+    }
+}
+
+pub(crate) fn compute<T, A>(node: Ref<T>, cache: &mut HashMap<Id<T>, A>) -> Option<A>
+where
+    A: Aggregate<T> + Clone
+{
+    if let Some(aggregate) = cache.get(&node.id()) {
+        return Some(aggregate.clone());
+    }
+
+    // Walk down to the nodes that aren't cached, and fold them back up in post-order.
+    // Each entry holds a node, its aggregate so far and the next child to visit.
+    let tree = node.tree();
+    let first = A::from_value(node.try_value()?);
+    let mut stack = vec![(node.id(), first, node.first_child_id())];
+
+    loop {
+        let child_id = stack.last().and_then(|&(_, _, child_id)| child_id);
+        match child_id {
+            Some(child_id) => {
+                let child = tree.get(child_id);
+                match cache.get(&child_id) {
+                    Some(aggregate) => {
+                        let top = stack.last_mut().expect("Empty stack");
+                        top.1 = top.1.combine(aggregate);
+                        top.2 = child.next_sibling_id();
+                    }
+                    None => {
+                        let value = A::from_value(child.try_value()?);
+                        stack.push((child_id, value, child.first_child_id()));
+                    }
+                }
+            }
+            None => {
+                let (id, aggregate, _) = stack.pop().expect("Empty stack");
+                cache.insert(id, aggregate.clone());
+                match stack.last_mut() {
+                    Some(top) => {
+                        top.1 = top.1.combine(&aggregate);
+                        top.2 = tree.get(id).next_sibling_id();
+                    }
+                    None => return Some(aggregate)
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,132 @@
+/*
+Copyright 2016 Mozilla
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+this file except in compliance with the License. You may obtain a copy of the
+License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed
+under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+CONDITIONS OF ANY KIND, either express or implied. See the License for the
+specific language governing permissions and limitations under the License.
+*/
+
+use types::{Node, Tree};
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Nested<T>(pub T, pub Vec<Nested<T>>);
+
+impl<T> From<(T, Vec<Nested<T>>)> for Nested<T> {
+    fn from((value, children): (T, Vec<Nested<T>>)) -> Self {
+        Nested(value, children)
+    }
+}
+
+// Values are buffered in pre-order and only linked into a tree once built.
+#[derive(Debug, PartialEq, Clone)]
+pub struct TreeBuilder<T> {
+    values: Vec<(usize, T)>,
+    depth: usize
+}
+
+impl<T> Default for TreeBuilder<T> {
+    fn default() -> Self {
+        TreeBuilder {
+            values: vec![],
+            depth: 0
+        }
+    }
+}
+
+impl<T> TreeBuilder<T> {
+    pub fn new() -> Self {
+        TreeBuilder::default()
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn open<U>(&mut self, value: U) -> &mut Self
+    where
+        U: Into<T>
+    {
+        self.values.push((self.depth, U::into(value)));
+        self.depth += 1;
+        self
+    }
+
+    pub fn close(&mut self) -> &mut Self {
+        self.depth = self.depth.saturating_sub(1);
+        self
+    }
+
+    pub fn leaf<U>(&mut self, value: U) -> &mut Self
+    where
+        U: Into<T>
+    {
+        self.values.push((self.depth, U::into(value)));
+        self
+    }
+
+    // Nodes left open are closed implicitly. Depths only grow one level per `open`, so
+    // the only way to fail is a second value at the root level.
+    pub fn build(self) -> Option<Tree<T>> {
+        Tree::from_preorder(self.values)
+    }
+}
+
+impl<T> Tree<T> {
+    pub fn reserve(&mut self, additional: usize) {
+        self.arena.reserve(additional);
+    }
+
+    pub fn from_nested<N>(nested: N) -> Tree<T>
+    where
+        N: Into<Nested<T>>
+    {
+        let mut values = vec![];
+        let mut stack = vec![(0, N::into(nested))];
+        while let Some((depth, Nested(value, children))) = stack.pop() {
+            values.push((depth, value));
+            stack.extend(children.into_iter().rev().map(|child| (depth + 1, child)));
+        }
+        Tree::from_preorder(values).expect("Missing root")
+    }
+
+    // The first value is the root, at depth 0. Each node is linked as the last child of
+    // the closest node above it, without going through the mutation primitives, which
+    // is why there is nothing to notify or record. Returns None for a second root, or
+    // for a value more than one level deeper than the one before it.
+    pub fn from_preorder<I>(values: I) -> Option<Tree<T>>
+    where
+        I: IntoIterator<Item = (usize, T)>
+    {
+        let mut values = values.into_iter();
+        let (_, value) = values.next()?;
+        let mut tree = Tree::new(value);
+        tree.reserve(values.size_hint().0);
+
+        let mut parent_ids = vec![tree.root];
+        for (depth, value) in values {
+            if depth == 0 || depth > parent_ids.len() {
+                return None;
+            }
+            parent_ids.truncate(depth);
+            let parent_id = *parent_ids.last()?;
+            let prev_sibling_id = tree.arena.get(parent_id)?.last_child_id;
+
+            let mut node = Node::new(value);
+            node.parent_id = Some(parent_id);
+            node.prev_sibling_id = prev_sibling_id;
+            let id = tree.arena.alloc(node);
+
+            match prev_sibling_id {
+                Some(prev_sibling_id) => tree.arena.get_mut(prev_sibling_id)?.next_sibling_id = Some(id),
+                None => tree.arena.get_mut(parent_id)?.first_child_id = Some(id)
+            }
+            tree.arena.get_mut(parent_id)?.last_child_id = Some(id);
+            parent_ids.push(id);
+        }
+
+        Some(tree)
+    }
+}
@@ -0,0 +1,288 @@
+/*
+Copyright 2016 Mozilla
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+this file except in compliance with the License. You may obtain a copy of the
+License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed
+under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+CONDITIONS OF ANY KIND, either express or implied. See the License for the
+specific language governing permissions and limitations under the License.
+*/
+
+use std::cmp;
+
+use types::{Edge, NodePath, Ref, Tree};
+
+// Paths are always relative to the tree as it is right before the edit is applied.
+#[derive(Debug, PartialEq)]
+pub enum Edit<T> {
+    Insert { path: NodePath, subtree: Tree<T> },
+    Delete { path: NodePath },
+    Move { from: NodePath, to: NodePath },
+    Update { path: NodePath, value: T }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Match {
+    InPlace(usize),
+    Moved(usize),
+    Inserted
+}
+
+impl Match {
+    fn old_index(&self) -> Option<usize> {
+        match *self {
+            Match::InPlace(i) | Match::Moved(i) => Some(i),
+            Match::Inserted => None
+        }
+    }
+}
+
+impl<T> Tree<T>
+where
+    T: PartialEq + Clone
+{
+    pub fn diff(&self, other: &Tree<T>) -> Vec<Edit<T>> {
+        let mut edits = vec![];
+        diff_nodes(self.get(self.root), other.get(other.root), &mut edits);
+        edits
+    }
+}
+
+impl<T> Tree<T>
+where
+    T: Clone
+{
+    pub fn apply_edits(&mut self, edits: &[Edit<T>]) -> Option<()> {
+        for edit in edits {
+            self.apply_edit(edit)?;
+        }
+        Some(())
+    }
+
+    pub fn apply_edit(&mut self, edit: &Edit<T>) -> Option<()> {
+        match *edit {
+            Edit::Insert {
+                ref path,
+                ref subtree
+            } => {
+                let index = *path.last()?;
+                let parent_id = self.resolve(&path.parent()?)?;
+                let id = self
+                    .get_mut(parent_id)
+                    .append_clone_of(&subtree.get(subtree.root()))?;
+                self.get_mut(id).move_to(parent_id, index)
+            }
+            Edit::Delete { ref path } => {
+                let id = self.resolve(path)?;
+                self.get(id).parent_id()?;
+                self.take_subtree(id).map(|_| ())
+            }
+            Edit::Move { ref from, ref to } => {
+                // The destination is resolved once the node has been taken out.
+                let id = self.resolve(from)?;
+                self.get_mut(id).detach_own_id()?;
+                let index = *to.last()?;
+                let parent_id = self.resolve(&to.parent()?)?;
+                self.get_mut(id).move_to(parent_id, index)
+            }
+            Edit::Update {
+                ref path,
+                ref value
+            } => {
+                let id = self.resolve(path)?;
+                self.get_mut(id).replace_with(value.clone());
+                Some(())
+            }
+        }
+    }
+}
+
+fn diff_nodes<T>(old: Ref<T>, new: Ref<T>, edits: &mut Vec<Edit<T>>)
+where
+    T: PartialEq + Clone
+{
+    // Pairs of matched nodes are visited in pre-order, like a recursive walk would.
+    let mut stack = vec![(old, new, NodePath::root())];
+
+    while let Some((old, new, path)) = stack.pop() {
+        if subtrees_equal(old, new) {
+            continue;
+        }
+
+        if let Some(value) = new.try_value() {
+            if old.try_value() != Some(value) {
+                let path = path.clone();
+                let value = value.clone();
+                edits.push(Edit::Update { path, value });
+            }
+        }
+
+        let old_children: Vec<_> = old.children_refs_iter().collect();
+        let new_children: Vec<_> = new.children_refs_iter().collect();
+        let matches = match_children(&old_children, &new_children);
+
+        let mut new_indices = vec![None; old_children.len()];
+        for (j, m) in matches.iter().enumerate() {
+            if let Some(i) = m.old_index() {
+                new_indices[i] = Some(j);
+            }
+        }
+
+        // Delete unmatched old children, last ones first so that indices stay valid.
+        for i in (0..old_children.len())
+            .rev()
+            .filter(|&i| new_indices[i].is_none())
+        {
+            let path = path.child(i);
+            edits.push(Edit::Delete { path });
+        }
+
+        // Every remaining child is tracked by its index in the new tree. Walking
+        // backwards, moved and inserted children are placed right before their next
+        // sibling, which is by then already in its final position.
+        let mut current: Vec<_> = new_indices.into_iter().filter_map(|j| j).collect();
+
+        for (j, m) in matches.iter().enumerate().rev() {
+            let anchor = current
+                .iter()
+                .position(|&v| v == j + 1)
+                .unwrap_or(current.len());
+            match *m {
+                Match::InPlace(_) => {}
+                Match::Moved(_) => {
+                    let from = current.iter().position(|&v| v == j).unwrap_or(anchor);
+                    let to = if from < anchor { anchor - 1 } else { anchor };
+                    if from != to {
+                        current.remove(from);
+                        current.insert(to, j);
+                        edits.push(Edit::Move {
+                            from: path.child(from),
+                            to: path.child(to)
+                        });
+                    }
+                }
+                Match::Inserted => {
+                    current.insert(anchor, j);
+                    let path = path.child(anchor);
+                    let subtree = new.tree().clone_subtree(new_children[j].id());
+                    edits.push(Edit::Insert { path, subtree });
+                }
+            }
+        }
+
+        // The children list now matches the new tree, so children are visited using
+        // new indices. They're pushed last first, so that the first one is popped next.
+        for (j, m) in matches.iter().enumerate().rev() {
+            if let Some(i) = m.old_index() {
+                stack.push((old_children[i], new_children[j], path.child(j)));
+            }
+        }
+    }
+}
+
+fn match_children<T>(old: &[Ref<T>], new: &[Ref<T>]) -> Vec<Match>
+where
+    T: PartialEq
+{
+    let mut matches = vec![Match::Inserted; new.len()];
+    let mut matched_old = vec![false; old.len()];
+
+    // Children with equal values that keep their relative order stay in place.
+    let common = longest_common_subsequence(old, new);
+    for &(i, j) in &common {
+        matches[j] = Match::InPlace(i);
+        matched_old[i] = true;
+    }
+
+    // Any other equal values have been reordered.
+    for j in 0..new.len() {
+        if matches[j] != Match::Inserted {
+            continue;
+        }
+        let found = (0..old.len()).find(|&i| !matched_old[i] && old[i].try_value() == new[j].try_value());
+        if let Some(i) = found {
+            matches[j] = Match::Moved(i);
+            matched_old[i] = true;
+        }
+    }
+
+    // Leftovers sitting between the same unchanged children are updated in place.
+    let old_gap = |i: usize| common.iter().take_while(|&&(v, _)| v < i).count();
+    let new_gap = |j: usize| common.iter().take_while(|&&(_, v)| v < j).count();
+    let mut leftover_old = (0..old.len()).filter(|&i| !matched_old[i]).peekable();
+
+    for j in 0..new.len() {
+        if matches[j] != Match::Inserted {
+            continue;
+        }
+        while leftover_old
+            .peek()
+            .map_or(false, |&i| old_gap(i) < new_gap(j))
+        {
+            leftover_old.next();
+        }
+        if leftover_old
+            .peek()
+            .map_or(false, |&i| old_gap(i) == new_gap(j))
+        {
+            matches[j] = Match::InPlace(leftover_old.next().unwrap());
+        }
+    }
+
+    matches
+}
+
+fn longest_common_subsequence<T>(old: &[Ref<T>], new: &[Ref<T>]) -> Vec<(usize, usize)>
+where
+    T: PartialEq
+{
+    let (n, m) = (old.len(), new.len());
+    let mut lengths = vec![vec![0; m + 1]; n + 1];
+
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lengths[i][j] = if old[i].try_value() == new[j].try_value() {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                cmp::max(lengths[i + 1][j], lengths[i][j + 1])
+            };
+        }
+    }
+
+    let mut pairs = vec![];
+    let (mut i, mut j) = (0, 0);
+    while i < n && j < m {
+        if old[i].try_value() == new[j].try_value() {
+            pairs.push((i, j));
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    pairs
+}
+
+fn subtrees_equal<T>(a: Ref<T>, b: Ref<T>) -> bool
+where
+    T: PartialEq
+{
+    let mut a_edges = a.traverse_edges_iter();
+    let mut b_edges = b.traverse_edges_iter();
+
+    loop {
+        match (a_edges.next(), b_edges.next()) {
+            (Some(Edge::Open(a)), Some(Edge::Open(b))) => {
+                if a.try_value() != b.try_value() {
+                    return false;
+                }
+            }
+            (Some(Edge::Close(_)), Some(Edge::Close(_))) => {}
+            (None, None) => return true,
+            _ => return false
+        }
+    }
+}
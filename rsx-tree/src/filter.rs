@@ -0,0 +1,69 @@
+/*
+Copyright 2016 Mozilla
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+this file except in compliance with the License. You may obtain a copy of the
+License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed
+under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+CONDITIONS OF ANY KIND, either express or implied. See the License for the
+specific language governing permissions and limitations under the License.
+*/
+
+use types::{Id, Ref, RefMut};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum FilterAction {
+    Keep,
+    Remove,
+    Unwrap
+}
+
+impl<'a, T: 'a> RefMut<'a, T> {
+    pub fn retain_descendants<F>(&mut self, mut f: F) -> usize
+    where
+        F: FnMut(Ref<T>) -> bool
+    {
+        self.filter_descendants(|node| match f(node) {
+            true => FilterAction::Keep,
+            false => FilterAction::Remove
+        })
+    }
+
+    // Descendants are visited in pre-order, and only ids yet to be visited are kept
+    // around, none of which can be inside a subtree being removed. Removed subtrees
+    // are deallocated, while unwrapped nodes hand their children over to their parent,
+    // which are then visited in turn. Returns the number of deallocated nodes.
+    pub fn filter_descendants<F>(&mut self, mut f: F) -> usize
+    where
+        F: FnMut(Ref<T>) -> FilterAction
+    {
+        let mut stack = self.children_ids_rev();
+        let mut removed = 0;
+
+        while let Some(id) = stack.pop() {
+            match f(self.tree.get(id)) {
+                FilterAction::Keep => {
+                    stack.extend(self.tree.get_mut(id).children_ids_rev());
+                }
+                FilterAction::Remove => {
+                    removed += self.tree.take_subtree(id).map_or(0, |values| values.len());
+                }
+                FilterAction::Unwrap => {
+                    let children = self.tree.get_mut(id).children_ids_rev();
+                    if self.tree.get_mut(id).unwrap_node().is_some() {
+                        removed += 1;
+                    }
+                    stack.extend(children);
+                }
+            }
+        }
+
+        removed
+    }
+
+    fn children_ids_rev(&self) -> Vec<Id<T>> {
+        let mut ids: Vec<_> = self.tree.get(self.id).children_ids_iter().collect();
+        ids.reverse();
+        ids
+    }
+}
@@ -0,0 +1,209 @@
+/*
+Copyright 2016 Mozilla
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+this file except in compliance with the License. You may obtain a copy of the
+License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed
+under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+CONDITIONS OF ANY KIND, either express or implied. See the License for the
+specific language governing permissions and limitations under the License.
+*/
+
+use types::{Id, Ref, RefMut, Tree};
+
+// All roots live in the arena of a single tree, which keeps its own root pointed at
+// the first one, and pins the others so that garbage collection keeps them. Roots
+// are expected to stay detached; attaching one to another node only makes sense
+// after removing it from the forest.
+#[derive(Debug, PartialEq)]
+pub struct Forest<T> {
+    raw: Option<Tree<T>>,
+    roots: Vec<Id<T>>
+}
+
+impl<T> Default for Forest<T> {
+    fn default() -> Self {
+        Forest {
+            raw: None,
+            roots: vec![]
+        }
+    }
+}
+
+impl<T> From<Tree<T>> for Forest<T> {
+    fn from(tree: Tree<T>) -> Self {
+        let roots = vec![tree.root()];
+        Forest {
+            raw: Some(tree),
+            roots
+        }
+    }
+}
+
+impl<T> Forest<T> {
+    pub fn new() -> Self {
+        Forest::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.roots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.roots.is_empty()
+    }
+
+    pub fn get(&self, id: Id<T>) -> Ref<T> {
+        self.raw.as_ref().expect("Empty forest").get(id)
+    }
+
+    pub fn get_mut(&mut self, id: Id<T>) -> RefMut<T> {
+        self.raw.as_mut().expect("Empty forest").get_mut(id)
+    }
+
+    pub fn is_root(&self, id: Id<T>) -> bool {
+        self.roots.contains(&id)
+    }
+
+    pub fn roots_ids_iter<'a>(&'a self) -> impl Iterator<Item = Id<T>> + 'a {
+        self.roots.iter().cloned()
+    }
+
+    pub fn roots_refs_iter<'a>(&'a self) -> impl Iterator<Item = Ref<'a, T>> + 'a {
+        self.roots.iter().map(move |&id| self.get(id))
+    }
+
+    pub fn roots_values_iter<'a>(&'a self) -> impl Iterator<Item = &'a T> + 'a {
+        self.roots_refs_iter()
+            .filter_map(|node| node.try_into_value())
+    }
+
+    pub fn push_root<U>(&mut self, value: U) -> Id<T>
+    where
+        U: Into<T>
+    {
+        let index = self.roots.len();
+        self.insert_root(index, value)
+    }
+
+    pub fn insert_root<U>(&mut self, index: usize, value: U) -> Id<T>
+    where
+        U: Into<T>
+    {
+        if self.raw.is_none() {
+            return self.insert_tree(index, Tree::new(value));
+        }
+
+        let id = {
+            let tree = self.raw.as_mut().expect("Empty forest");
+            let id = tree.alloc(value);
+            tree.pin(id);
+            id
+        };
+        self.roots.insert(index, id);
+        self.sync_root();
+        id
+    }
+
+    pub fn push_tree(&mut self, tree: Tree<T>) -> Id<T> {
+        let index = self.roots.len();
+        self.insert_tree(index, tree)
+    }
+
+    // Values are moved over one by one, since not every arena can be merged into another.
+    pub fn insert_tree(&mut self, index: usize, mut tree: Tree<T>) -> Id<T> {
+        if self.raw.is_none() {
+            let id = tree.root();
+            tree.pin(id);
+            self.raw = Some(tree);
+            self.roots.insert(index, id);
+            return id;
+        }
+
+        let mut values = tree.into_preorder().into_iter();
+        let (_, value) = values.next().expect("Node deallocated");
+        let id = self.insert_root(index, value);
+        self.get_mut(id)
+            .append_preorder(values.map(|(depth, value)| (depth - 1, value)));
+        id
+    }
+
+    pub fn remove_root(&mut self, id: Id<T>) -> Option<Tree<T>> {
+        let index = self.roots.iter().position(|&root_id| root_id == id)?;
+        self.roots.remove(index);
+        self.raw.as_mut()?.unpin(id);
+
+        if self.roots.is_empty() {
+            return self.raw.take();
+        }
+
+        self.sync_root();
+        self.raw.as_mut()?.split_off(id)
+    }
+
+    pub fn into_trees(mut self) -> Vec<Tree<T>> {
+        let ids = self.roots.clone();
+        ids.into_iter()
+            .filter_map(|id| self.remove_root(id))
+            .collect()
+    }
+
+    pub fn into_tree<U>(self, root: U) -> Tree<T>
+    where
+        U: Into<T>
+    {
+        let Forest { raw, roots } = self;
+        let mut tree = match raw {
+            Some(tree) => tree,
+            None => return Tree::new(root)
+        };
+        let root_id = tree.alloc(root);
+        tree.root = root_id;
+        for id in roots {
+            tree.unpin(id);
+            tree.get_mut(root_id).append_id(id);
+        }
+        tree
+    }
+
+    fn sync_root(&mut self) {
+        if let (Some(tree), Some(&id)) = (self.raw.as_mut(), self.roots.first()) {
+            tree.root = id;
+        }
+    }
+}
+
+impl<T> Tree<T> {
+    // The root is dropped and each of its children becomes a root of the forest.
+    pub fn into_forest(mut self) -> (T, Forest<T>) {
+        let root_id = self.root;
+        let roots: Vec<_> = self.get(root_id).children_ids_iter().collect();
+        for &id in &roots {
+            self.get_mut(id).detach();
+            self.pin(id);
+        }
+
+        let value = self.dealloc(root_id).expect("Node deallocated").value;
+        match roots.first() {
+            Some(&id) => self.root = id,
+            None => return (value, Forest::new())
+        }
+
+        let forest = Forest {
+            raw: Some(self),
+            roots
+        };
+        (value, forest)
+    }
+
+    pub(crate) fn into_preorder(mut self) -> Vec<(usize, T)> {
+        let ids: Vec<_> = self
+            .get(self.root)
+            .descendants_depths_iter()
+            .map(|(depth, node)| (depth, node.id()))
+            .collect();
+        ids.into_iter()
+            .filter_map(|(depth, id)| Some((depth, self.arena.dealloc(id)?.value)))
+            .collect()
+    }
+}
@@ -0,0 +1,63 @@
+/*
+Copyright 2016 Mozilla
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+this file except in compliance with the License. You may obtain a copy of the
+License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed
+under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+CONDITIONS OF ANY KIND, either express or implied. See the License for the
+specific language governing permissions and limitations under the License.
+*/
+
+use std::collections::HashSet;
+
+use types::{Id, Tree};
+
+impl<T> Tree<T> {
+    // Pinned nodes are kept alive by garbage collection, along with the whole subtree
+    // hanging from their topmost ancestor, so that no kept node links to a freed one.
+    pub fn pin(&mut self, id: Id<T>) {
+        self.pinned.insert(id);
+    }
+
+    pub fn unpin(&mut self, id: Id<T>) -> bool {
+        self.pinned.remove(&id)
+    }
+
+    pub fn is_pinned(&self, id: Id<T>) -> bool {
+        self.pinned.contains(&id)
+    }
+
+    pub fn orphans(&self) -> Vec<Id<T>> {
+        let reachable = self.reachable();
+        self.arena
+            .iter()
+            .map(|(id, _)| id)
+            .filter(|id| !reachable.contains(id))
+            .collect()
+    }
+
+    pub fn collect_garbage(&mut self) -> usize {
+        let orphans = self.orphans();
+        orphans
+            .into_iter()
+            .filter(|&id| self.dealloc(id).is_some())
+            .count()
+    }
+
+    fn reachable(&self) -> HashSet<Id<T>> {
+        let mut reachable = HashSet::new();
+        let pinned = self
+            .pinned
+            .iter()
+            .filter(|&&id| self.arena.get(id).is_some());
+        for &id in Some(&self.root).into_iter().chain(pinned) {
+            let top_id = self.get(id).ancestors_ids_iter().last().unwrap_or(id);
+            if reachable.contains(&top_id) {
+                continue;
+            }
+            reachable.extend(self.get(top_id).descendants_ids_iter());
+        }
+        reachable
+    }
+}
@@ -0,0 +1,68 @@
+/*
+Copyright 2016 Mozilla
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+this file except in compliance with the License. You may obtain a copy of the
+License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed
+under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+CONDITIONS OF ANY KIND, either express or implied. See the License for the
+specific language governing permissions and limitations under the License.
+*/
+
+use std::cell::RefCell;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+use aggregate::compute;
+use types::{Aggregate, Ref, Tree};
+
+// Hashes each value together with the hashes of its children, in order, so equal
+// subtrees hash equally no matter where they are or which tree they're in.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct SubtreeHash(pub u64);
+
+impl<T> Aggregate<T> for SubtreeHash
+where
+    T: Hash
+{
+    fn from_value(value: &T) -> Self {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        SubtreeHash(hasher.finish())
+    }
+
+    fn combine(&self, other: &Self) -> Self {
+        let mut hasher = DefaultHasher::new();
+        self.0.hash(&mut hasher);
+        other.0.hash(&mut hasher);
+        SubtreeHash(hasher.finish())
+    }
+}
+
+impl<T> Tree<T>
+where
+    T: Hash
+{
+    // Keeps subtree hashes around, and only rehashes the ancestors of changed nodes.
+    pub fn enable_subtree_hashes(&mut self) {
+        self.aggregates.hashes = Some(RefCell::new(HashMap::new()));
+    }
+
+    pub fn disable_subtree_hashes(&mut self) {
+        self.aggregates.hashes = None;
+    }
+}
+
+impl<'a, T> Ref<'a, T>
+where
+    T: Hash
+{
+    pub fn subtree_hash(&self) -> u64 {
+        let hash = match self.tree().aggregates.hashes {
+            Some(ref hashes) => compute(*self, &mut hashes.borrow_mut()),
+            None => compute(*self, &mut HashMap::new())
+        };
+        hash.expect("Node deallocated").0
+    }
+}
@@ -0,0 +1,385 @@
+/*
+Copyright 2016 Mozilla
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+this file except in compliance with the License. You may obtain a copy of the
+License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed
+under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+CONDITIONS OF ANY KIND, either express or implied. See the License for the
+specific language governing permissions and limitations under the License.
+*/
+
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
+use std::mem;
+
+use node::Links;
+use types::{Id, Node, Tree};
+
+const DEFAULT_HISTORY_LIMIT: usize = 100;
+
+// Values are copied before being handed out during a transaction, so that they can be
+// put back on undo. Anything `Clone` can be, other types can decide what to keep.
+pub trait Snapshot: Sized {
+    fn snapshot(&self) -> Self;
+
+    // Puts a snapshot back in place of this value, and returns what it replaced.
+    fn restore(&mut self, snapshot: Self) -> Self {
+        mem::replace(self, snapshot)
+    }
+}
+
+impl<T> Snapshot for T
+where
+    T: Clone
+{
+    fn snapshot(&self) -> Self {
+        self.clone()
+    }
+}
+
+pub(crate) enum Change<T> {
+    Links {
+        id: Id<T>,
+        links: Links<T>
+    },
+    Value {
+        id: Id<T>,
+        value: T
+    },
+    Root {
+        id: Id<T>
+    },
+    Node {
+        id: Id<T>,
+        node: Node<T>
+    },
+    Vacant {
+        id: Id<T>
+    },
+    Hook {
+        parents: Vec<(Id<T>, Vec<Id<T>>)>,
+        attach: bool
+    }
+}
+
+impl<T> Change<T> {
+    fn mentions(&self, id: Id<T>) -> bool {
+        match *self {
+            Change::Links {
+                id: other_id,
+                ref links
+            } => other_id == id || links.mentions(id),
+            Change::Node {
+                id: other_id,
+                ref node
+            } => other_id == id || node.links().mentions(id),
+            Change::Value { id: other_id, .. } | Change::Root { id: other_id } | Change::Vacant { id: other_id } => other_id == id,
+            Change::Hook { ref parents, .. } => parents
+                .iter()
+                .any(|&(parent_id, ref child_ids)| parent_id == id || child_ids.contains(&id))
+        }
+    }
+
+    // Values can be put back whatever happened to the links around them.
+    fn mentions_links(&self, id: Id<T>) -> bool {
+        match *self {
+            Change::Value { .. } => false,
+            _ => self.mentions(id)
+        }
+    }
+}
+
+pub(crate) struct History<T> {
+    depth: usize,
+    current: Vec<Change<T>>,
+    values: HashSet<Id<T>>,
+    undo: VecDeque<Vec<Change<T>>>,
+    redo: Vec<Vec<Change<T>>>,
+    limit: usize,
+    snapshot_fn: Option<fn(&T) -> T>,
+    restore_fn: Option<fn(&mut T, T) -> T>
+}
+
+impl<T> Default for History<T> {
+    fn default() -> Self {
+        History {
+            depth: 0,
+            current: vec![],
+            values: HashSet::new(),
+            undo: VecDeque::new(),
+            redo: vec![],
+            limit: DEFAULT_HISTORY_LIMIT,
+            snapshot_fn: None,
+            restore_fn: None
+        }
+    }
+}
+
+impl<T> fmt::Debug for History<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "History({}, {})", self.undo.len(), self.redo.len())
+    }
+}
+
+// Like observers, the history never makes two trees different.
+impl<T> PartialEq for History<T> {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl<T> Tree<T>
+where
+    T: Snapshot
+{
+    pub fn begin(&mut self) {
+        self.history.snapshot_fn = Some(T::snapshot);
+        self.history.restore_fn = Some(T::restore);
+        self.history.depth += 1;
+    }
+}
+
+impl<T> Tree<T> {
+    pub fn is_recording(&self) -> bool {
+        self.history.depth > 0
+    }
+
+    pub fn commit(&mut self) -> bool {
+        if self.history.depth == 0 {
+            return false;
+        }
+
+        // Nested transactions are folded into the outermost one.
+        self.history.depth -= 1;
+        if self.history.depth > 0 {
+            return true;
+        }
+
+        let changes = mem::replace(&mut self.history.current, vec![]);
+        self.history.values.clear();
+        if !changes.is_empty() {
+            self.history.undo.push_back(changes);
+            if self.history.undo.len() > self.history.limit {
+                self.history.undo.pop_front();
+            }
+            self.history.redo.clear();
+        }
+
+        true
+    }
+
+    pub fn rollback(&mut self) -> bool {
+        self.rollback_with(|_, _, _, _| {})
+    }
+
+    pub fn rollback_with<F>(&mut self, mut hook: F) -> bool
+    where
+        F: FnMut(&mut Tree<T>, Id<T>, &[Id<T>], bool)
+    {
+        if self.history.depth == 0 {
+            return false;
+        }
+
+        self.history.depth = 0;
+        let changes = mem::replace(&mut self.history.current, vec![]);
+        self.history.values.clear();
+        self.apply_changes(changes, &mut hook);
+        true
+    }
+
+    pub fn undo(&mut self) -> bool {
+        self.undo_with(|_, _, _, _| {})
+    }
+
+    pub fn undo_with<F>(&mut self, mut hook: F) -> bool
+    where
+        F: FnMut(&mut Tree<T>, Id<T>, &[Id<T>], bool)
+    {
+        if self.is_recording() {
+            return false;
+        }
+        match self.history.undo.pop_back() {
+            Some(changes) => {
+                let changes = self.apply_changes(changes, &mut hook);
+                self.history.redo.push(changes);
+                true
+            }
+            None => false
+        }
+    }
+
+    pub fn redo(&mut self) -> bool {
+        self.redo_with(|_, _, _, _| {})
+    }
+
+    pub fn redo_with<F>(&mut self, mut hook: F) -> bool
+    where
+        F: FnMut(&mut Tree<T>, Id<T>, &[Id<T>], bool)
+    {
+        if self.is_recording() {
+            return false;
+        }
+        match self.history.redo.pop() {
+            Some(changes) => {
+                let changes = self.apply_changes(changes, &mut hook);
+                self.history.undo.push_back(changes);
+                true
+            }
+            None => false
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.history.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.history.redo.is_empty()
+    }
+
+    pub fn set_history_limit(&mut self, limit: usize) {
+        self.history.limit = limit;
+        while self.history.undo.len() > limit {
+            self.history.undo.pop_front();
+        }
+    }
+
+    pub fn clear_history(&mut self) {
+        self.history.undo.clear();
+        self.history.redo.clear();
+    }
+
+    // Hooks let owners of state tied to the tree links (like layout nodes) take part
+    // in undo. When reverted, `hook` is called for each parent with its children and
+    // `attach` set, and the inverted hook is recorded in its place.
+    pub fn record_hook(&mut self, parents: Vec<(Id<T>, Vec<Id<T>>)>, attach: bool) {
+        self.record(Change::Hook { parents, attach });
+    }
+
+    pub(crate) fn record(&mut self, change: Change<T>) {
+        if self.is_recording() {
+            self.history.current.push(change);
+        }
+    }
+
+    // Links changed outside of a transaction would be overwritten by an undo, so the
+    // changes that rely on them are dropped instead.
+    pub(crate) fn node_mut(&mut self, id: Id<T>) -> Option<&mut Node<T>> {
+        if self.is_recording() {
+            let links = self.arena.get(id)?.links();
+            self.history.current.push(Change::Links { id, links });
+        } else {
+            self.purge_history(|change| change.mentions_links(id));
+        }
+        self.arena.get_mut(id)
+    }
+
+    pub(crate) fn set_root(&mut self, id: Id<T>) {
+        let old_id = mem::replace(&mut self.root, id);
+        if self.is_recording() {
+            self.history.current.push(Change::Root { id: old_id });
+        } else {
+            self.purge_history(|change| change.mentions_links(old_id) || change.mentions_links(id));
+        }
+    }
+
+    // Values handed out by reference might change, so a snapshot is kept. Changes are
+    // reverted in reverse order, so the first snapshot in a transaction is enough.
+    pub(crate) fn record_value(&mut self, id: Id<T>) {
+        if let (true, Some(snapshot_fn)) = (self.is_recording(), self.history.snapshot_fn) {
+            if !self.history.values.insert(id) {
+                return;
+            }
+            if let Some(node) = self.arena.get(id) {
+                let value = snapshot_fn(&node.value);
+                self.history.current.push(Change::Value { id, value });
+            }
+        }
+    }
+
+    // Deallocated nodes are kept in the transaction, so that they can be put back.
+    // Outside of one, whatever was recorded about them can't be reverted anymore.
+    pub(crate) fn record_dealloc(&mut self, id: Id<T>) {
+        if let (true, Some(snapshot_fn)) = (self.is_recording(), self.history.snapshot_fn) {
+            if let Some(node) = self.arena.get(id) {
+                let mut copy = Node::new(snapshot_fn(&node.value));
+                copy.replace_links(node.links());
+                self.history.current.push(Change::Node { id, node: copy });
+            }
+        } else {
+            self.purge_history(|change| change.mentions(id));
+        }
+    }
+
+    // Older transactions are only valid on top of newer ones, so they're dropped too.
+    fn purge_history<F>(&mut self, f: F)
+    where
+        F: Fn(&Change<T>) -> bool
+    {
+        if self.history.undo.is_empty() && self.history.redo.is_empty() {
+            return;
+        }
+        let mentions = |changes: &Vec<Change<T>>| changes.iter().any(&f);
+        if let Some(index) = self.history.undo.iter().rposition(&mentions) {
+            self.history.undo.drain(..index + 1);
+        }
+        if let Some(index) = self.history.redo.iter().rposition(&mentions) {
+            self.history.redo.drain(..index + 1);
+        }
+    }
+
+    fn apply_changes<F>(&mut self, changes: Vec<Change<T>>, hook: &mut F) -> Vec<Change<T>>
+    where
+        F: FnMut(&mut Tree<T>, Id<T>, &[Id<T>], bool)
+    {
+        // Reverted changes aren't reported, so cached aggregates can't be trusted.
+        self.aggregates.clear();
+        changes
+            .into_iter()
+            .rev()
+            .map(|change| self.apply_change(change, hook))
+            .collect()
+    }
+
+    fn apply_change<F>(&mut self, change: Change<T>, hook: &mut F) -> Change<T>
+    where
+        F: FnMut(&mut Tree<T>, Id<T>, &[Id<T>], bool)
+    {
+        match change {
+            Change::Links { id, links } => {
+                let node = self.arena.get_mut(id).expect("Node deallocated");
+                let links = node.replace_links(links);
+                Change::Links { id, links }
+            }
+            Change::Value { id, value } => {
+                let restore_fn = self
+                    .history
+                    .restore_fn
+                    .expect("Value recorded without a transaction");
+                let node = self.arena.get_mut(id).expect("Node deallocated");
+                let value = restore_fn(&mut node.value, value);
+                Change::Value { id, value }
+            }
+            Change::Root { id } => {
+                let id = mem::replace(&mut self.root, id);
+                Change::Root { id }
+            }
+            Change::Node { id, node } => {
+                self.arena.restore(id, node);
+                Change::Vacant { id }
+            }
+            Change::Vacant { id } => {
+                let node = self.arena.dealloc(id).expect("Node deallocated");
+                Change::Node { id, node }
+            }
+            Change::Hook { parents, attach } => {
+                for &(id, ref child_ids) in &parents {
+                    hook(self, id, child_ids, attach);
+                }
+                let attach = !attach;
+                Change::Hook { parents, attach }
+            }
+        }
+    }
+}
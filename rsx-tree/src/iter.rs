@@ -58,6 +58,45 @@ impl<'a, T: 'a> Iterator for Children<'a, T> {
     }
 }
 
+#[derive(Debug)]
+pub struct Ancestors<'a, T: 'a> {
+    next: Option<Ref<'a, T>>
+}
+
+impl<'a, 'b, T: 'a> From<&'b Ref<'a, T>> for Ancestors<'a, T> {
+    fn from(node: &Ref<'a, T>) -> Self {
+        Ancestors {
+            next: node.parent()
+        }
+    }
+}
+
+impl<'a, T: 'a> Eq for Ancestors<'a, T> {}
+
+impl<'a, T: 'a> PartialEq for Ancestors<'a, T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.next == other.next
+    }
+}
+
+impl<'a, T: 'a> Copy for Ancestors<'a, T> {}
+
+impl<'a, T: 'a> Clone for Ancestors<'a, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, T: 'a> Iterator for Ancestors<'a, T> {
+    type Item = Ref<'a, T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.next.take();
+        self.next = node.and_then(|v| v.parent());
+        node
+    }
+}
+
 #[derive(Debug)]
 pub enum Edge<'a, T: 'a> {
     Open(Ref<'a, T>),
@@ -11,22 +11,48 @@ specific language governing permissions and limitations under the License.
 
 #![feature(conservative_impl_trait)]
 
+#[cfg(feature = "rayon")]
+extern crate rayon;
 extern crate rsx_arena;
 
+mod aggregate;
+mod builder;
+mod diff;
+mod filter;
+mod forest;
+mod gc;
+mod hash;
+mod history;
 mod iter;
 mod node;
 mod node_id;
 mod node_ref;
 mod node_ref_mut;
 mod node_ref_mut_pair;
+mod observer;
+#[cfg(feature = "rayon")]
+mod parallel;
+mod path;
+mod range;
 mod tree;
+mod walk;
 
 pub mod types {
+    pub use aggregate::*;
+    pub use builder::*;
+    pub use diff::*;
+    pub use filter::*;
+    pub use forest::*;
+    pub use hash::*;
+    pub use history::*;
     pub use iter::*;
     pub use node::*;
     pub use node_id::*;
     pub use node_ref::*;
     pub use node_ref_mut::*;
     pub use node_ref_mut_pair::*;
+    pub use observer::*;
+    pub use path::*;
     pub use tree::*;
+    pub use walk::*;
 }
@@ -11,7 +11,7 @@ specific language governing permissions and limitations under the License.
 
 use types::Id;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct Node<T> {
     pub(crate) parent_id: Option<Id<T>>,
     pub(crate) prev_sibling_id: Option<Id<T>>,
@@ -33,3 +33,54 @@ impl<T> Node<T> {
         }
     }
 }
+
+pub(crate) struct Links<T> {
+    parent_id: Option<Id<T>>,
+    prev_sibling_id: Option<Id<T>>,
+    next_sibling_id: Option<Id<T>>,
+    first_child_id: Option<Id<T>>,
+    last_child_id: Option<Id<T>>
+}
+
+impl<T> Copy for Links<T> {}
+
+impl<T> Clone for Links<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Links<T> {
+    pub(crate) fn mentions(&self, id: Id<T>) -> bool {
+        [
+            self.parent_id,
+            self.prev_sibling_id,
+            self.next_sibling_id,
+            self.first_child_id,
+            self.last_child_id
+        ]
+        .contains(&Some(id))
+    }
+}
+
+impl<T> Node<T> {
+    pub(crate) fn links(&self) -> Links<T> {
+        Links {
+            parent_id: self.parent_id,
+            prev_sibling_id: self.prev_sibling_id,
+            next_sibling_id: self.next_sibling_id,
+            first_child_id: self.first_child_id,
+            last_child_id: self.last_child_id
+        }
+    }
+
+    pub(crate) fn replace_links(&mut self, links: Links<T>) -> Links<T> {
+        let old_links = self.links();
+        self.parent_id = links.parent_id;
+        self.prev_sibling_id = links.prev_sibling_id;
+        self.next_sibling_id = links.next_sibling_id;
+        self.first_child_id = links.first_child_id;
+        self.last_child_id = links.last_child_id;
+        old_links
+    }
+}
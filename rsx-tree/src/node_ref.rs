@@ -9,7 +9,7 @@ CONDITIONS OF ANY KIND, either express or implied. See the License for the
 specific language governing permissions and limitations under the License.
 */
 
-use types::{Children, Descendants, Id, Node, Traverse, Tree};
+use types::{Ancestors, Children, Descendants, Edge, Id, Node, Traverse, Tree};
 
 #[derive(Debug)]
 pub struct Ref<'a, T: 'a> {
@@ -117,6 +117,33 @@ impl<'a, T: 'a> Ref<'a, T> {
         }
     }
 
+    pub fn child_index(&self) -> Option<usize> {
+        self.parent_id()?;
+        let mut index = 0;
+        let mut prev_sibling = self.prev_sibling();
+        while let Some(node) = prev_sibling {
+            index += 1;
+            prev_sibling = node.prev_sibling();
+        }
+        Some(index)
+    }
+
+    pub fn ancestors_refs_iter(&self) -> Ancestors<'a, T> {
+        Ancestors::from(self)
+    }
+
+    pub fn ancestors_ids_iter(&self) -> impl Iterator<Item = Id<T>> + 'a {
+        self.ancestors_refs_iter().map(|v| v.id())
+    }
+
+    pub fn ancestors_values_iter(&self) -> impl Iterator<Item = &'a T> {
+        self.ancestors_refs_iter().filter_map(|v| v.try_value())
+    }
+
+    pub fn is_ancestor_of(&self, id: Id<T>) -> bool {
+        self.tree.get(id).ancestors_ids_iter().any(|v| v == self.id)
+    }
+
     pub fn children_refs_iter(&self) -> Children<'a, T> {
         Children::from(self)
     }
@@ -141,6 +168,21 @@ impl<'a, T: 'a> Ref<'a, T> {
         self.descendants_refs_iter().filter_map(|v| v.try_value())
     }
 
+    pub fn descendants_depths_iter(&self) -> impl Iterator<Item = (usize, Ref<'a, T>)> {
+        let mut depth = 0;
+        self.traverse_edges_iter()
+            .filter_map(move |edge| match edge {
+                Edge::Open(node) => {
+                    depth += 1;
+                    Some((depth - 1, node))
+                }
+                Edge::Close(_) => {
+                    depth -= 1;
+                    None
+                }
+            })
+    }
+
     pub fn traverse_edges_iter(&self) -> Traverse<'a, T> {
         Traverse::from(self)
     }
@@ -156,4 +198,16 @@ impl<'a, T: 'a> Ref<'a, T> {
     pub fn traverse_values_iter(&self) -> impl Iterator<Item = &'a T> {
         self.traverse_refs_iter().filter_map(|v| v.try_value())
     }
+
+    // Visits the subtree in post-order, so every node comes after all of its descendants.
+    pub fn fold_subtree<B, F>(&self, init: B, mut f: F) -> B
+    where
+        F: FnMut(B, Ref<'a, T>) -> B
+    {
+        self.traverse_edges_iter()
+            .fold(init, |acc, edge| match edge {
+                Edge::Open(_) => acc,
+                Edge::Close(node) => f(acc, node)
+            })
+    }
 }
@@ -9,12 +9,17 @@ CONDITIONS OF ANY KIND, either express or implied. See the License for the
 specific language governing permissions and limitations under the License.
 */
 
-use types::{Id, Ref, Tree};
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::mem;
+
+use history::Change;
+use types::{Id, MutationRecord, Ref, Tree, Walk, WalkRefMut};
 
 #[derive(Debug)]
 pub struct RefMut<'a, T: 'a> {
-    tree: &'a mut Tree<T>,
-    id: Id<T>
+    pub(crate) tree: &'a mut Tree<T>,
+    pub(crate) id: Id<T>
 }
 
 impl<'a, T: 'a> Eq for RefMut<'a, T> {}
@@ -52,14 +57,36 @@ impl<'a, T: 'a> RefMut<'a, T> {
         Some(&self.tree.arena.get(self.id)?.value)
     }
 
+    // Observers are told before the value is handed out, so they can't look at the
+    // change itself. Use `update` to report it once it's done.
     pub fn try_value_mut(&mut self) -> Option<&mut T> {
+        self.tree.arena.get(self.id)?;
+        self.tree.notify(MutationRecord::ValueMut { id: self.id });
+        self.value_mut()
+    }
+
+    fn value_mut(&mut self) -> Option<&mut T> {
+        self.tree.invalidate_aggregate(self.id);
+        self.tree.record_value(self.id);
         Some(&mut self.tree.arena.get_mut(self.id)?.value)
     }
 
     pub fn try_into_value(self) -> Option<&'a mut T> {
+        self.tree.invalidate_aggregate(self.id);
+        self.tree.record_value(self.id);
         Some(&mut self.tree.arena.get_mut(self.id)?.value)
     }
 
+    // Observers are told about the change once `f` is done, unless it returns `None`.
+    pub fn update<F, R>(&mut self, f: F) -> Option<R>
+    where
+        F: FnOnce(&mut T) -> Option<R>
+    {
+        let result = f(self.value_mut()?)?;
+        self.tree.notify(MutationRecord::ValueMut { id: self.id });
+        Some(result)
+    }
+
     pub fn parent_id(&self) -> Option<Id<T>> {
         self.tree.arena.get(self.id)?.parent_id
     }
@@ -121,6 +148,10 @@ impl<'a, T: 'a> RefMut<'a, T> {
         }
     }
 
+    pub fn child_index(&self) -> Option<usize> {
+        self.tree.get(self.id).child_index()
+    }
+
     pub fn append_tree(&mut self, other: Tree<T>) -> bool {
         let children: Vec<_> = other.get(other.root()).children_ids_iter().collect();
         self.tree.arena += other.arena;
@@ -145,35 +176,198 @@ impl<'a, T: 'a> RefMut<'a, T> {
         self.tree.get_mut(id)
     }
 
+    pub fn insert_before(&mut self, value: T) -> RefMut<T> {
+        let id = self.tree.alloc(value);
+        self.insert_id_before(id);
+        self.tree.get_mut(id)
+    }
+
+    pub fn insert_after(&mut self, value: T) -> RefMut<T> {
+        let id = self.tree.alloc(value);
+        self.insert_id_after(id);
+        self.tree.get_mut(id)
+    }
+
     pub fn detach(&mut self) {
         self.detach_own_id();
     }
 
+    pub fn replace_with(&mut self, value: T) -> T {
+        self.tree.record_value(self.id);
+        let old_value = {
+            let this_node = self.tree.arena.get_mut(self.id).expect("Node deallocated");
+            mem::replace(&mut this_node.value, value)
+        };
+        self.tree.notify(MutationRecord::ValueMut { id: self.id });
+        old_value
+    }
+
+    pub fn set_value(&mut self, value: T) {
+        let value = {
+            let this_node = self.tree.arena.get_mut(self.id).expect("Node deallocated");
+            mem::replace(&mut this_node.value, value)
+        };
+        self.tree.record(Change::Value { id: self.id, value });
+        self.tree.notify(MutationRecord::ValueMut { id: self.id });
+    }
+
+    pub fn replace_with_subtree(&mut self, id: Id<T>) -> Option<()> {
+        if id == self.id {
+            return Some(());
+        }
+
+        // A node can't take the place of one of its own descendants.
+        if self.tree.get(id).is_ancestor_of(self.id) {
+            return None;
+        }
+
+        self.parent_id()?;
+        self.tree.get_mut(id).detach_own_id();
+        self.insert_id_before(id)?;
+        self.detach_own_id()
+    }
+
+    pub fn move_to(&mut self, new_parent_id: Id<T>, position: usize) -> Option<()> {
+        // A node can't be moved underneath itself.
+        if new_parent_id == self.id || self.tree.get(self.id).is_ancestor_of(new_parent_id) {
+            return None;
+        }
+
+        // Both nodes have to exist, so that the insert can't fail after detaching.
+        self.tree.arena.get(self.id)?;
+        self.tree.arena.get(new_parent_id)?;
+
+        self.detach_own_id();
+        self.tree
+            .get_mut(new_parent_id)
+            .insert_id(self.id, position)
+    }
+
+    pub fn sort_children_by<F>(&mut self, mut compare: F) -> Option<()>
+    where
+        F: FnMut(&T, &T) -> Ordering
+    {
+        let ids: Vec<_> = {
+            let mut children = self
+                .tree
+                .get(self.id)
+                .children_refs_iter()
+                .map(|v| Some((v.id(), v.try_into_value()?)))
+                .collect::<Option<Vec<_>>>()?;
+            children.sort_by(|a, b| compare(a.1, b.1));
+            children.into_iter().map(|(id, _)| id).collect()
+        };
+        self.relink_children(&ids)
+    }
+
+    pub fn sort_children_by_key<K, F>(&mut self, mut f: F) -> Option<()>
+    where
+        K: Ord,
+        F: FnMut(&T) -> K
+    {
+        self.sort_children_by(|a, b| {
+            let a = f(a);
+            let b = f(b);
+            a.cmp(&b)
+        })
+    }
+
+    pub fn reverse_children(&mut self) -> Option<()> {
+        let mut ids: Vec<_> = self.tree.get(self.id).children_ids_iter().collect();
+        ids.reverse();
+        self.relink_children(&ids)
+    }
+
+    pub fn reorder_children(&mut self, ids: &[Id<T>]) -> Option<()> {
+        // The new order must be a permutation of the current children.
+        let child_ids: HashSet<_> = self.tree.get(self.id).children_ids_iter().collect();
+        let new_ids: HashSet<_> = ids.iter().cloned().collect();
+        if ids.len() != child_ids.len() || new_ids != child_ids {
+            return None;
+        }
+
+        self.relink_children(ids)
+    }
+
+    fn relink_children(&mut self, ids: &[Id<T>]) -> Option<()> {
+        let mut prev_sibling_id = None;
+
+        // Update each child's prev and next siblings.
+        for (index, &id) in ids.iter().enumerate() {
+            let child_node = self.tree.node_mut(id)?;
+            child_node.prev_sibling_id = prev_sibling_id;
+            child_node.next_sibling_id = ids.get(index + 1).cloned();
+            prev_sibling_id = Some(id);
+        }
+
+        // Update this node's first and last child.
+        {
+            let this_node = self.tree.node_mut(self.id)?;
+            this_node.first_child_id = ids.first().cloned();
+            this_node.last_child_id = ids.last().cloned();
+        }
+
+        self.tree
+            .notify(MutationRecord::Reorder { parent_id: self.id });
+        Some(())
+    }
+
+    pub fn unwrap_node(&mut self) -> Option<T> {
+        self.parent_id()?;
+
+        // Move every child out in front of this node, then drop the empty node.
+        while let Some(child_id) = self.first_child_id() {
+            self.tree.get_mut(child_id).detach_own_id()?;
+            self.insert_id_before(child_id)?;
+        }
+
+        self.detach_own_id()?;
+        Some(self.tree.dealloc(self.id)?.value)
+    }
+
+    pub fn wrap(&mut self, value: T) -> RefMut<T> {
+        let wrapper_id = self.tree.alloc(value);
+
+        if self.parent_id().is_some() {
+            self.insert_id_before(wrapper_id);
+            self.detach_own_id();
+        } else if self.tree.root == self.id {
+            self.tree.set_root(wrapper_id);
+        }
+
+        self.tree.get_mut(wrapper_id).append_id(self.id);
+        self.tree.get_mut(wrapper_id)
+    }
+
     pub fn append_id(&mut self, child_id: Id<T>) -> Option<()> {
         let old_last_child_id = self.tree.arena.get(self.id)?.last_child_id;
 
         // Update the new node's parent and previous sibling.
         {
-            let new_child_node = self.tree.arena.get_mut(child_id)?;
+            let new_child_node = self.tree.node_mut(child_id)?;
             new_child_node.parent_id = Some(self.id);
             new_child_node.prev_sibling_id = old_last_child_id;
         }
 
         // Update the old last child's next sibling.
         if let Some(old_last_child_id) = old_last_child_id {
-            let last_child_node = self.tree.arena.get_mut(old_last_child_id)?;
+            let last_child_node = self.tree.node_mut(old_last_child_id)?;
             last_child_node.next_sibling_id = Some(child_id);
         }
 
         // Update this node's first and last child.
         {
-            let this_node = self.tree.arena.get_mut(self.id)?;
+            let this_node = self.tree.node_mut(self.id)?;
             this_node.last_child_id = Some(child_id);
             if this_node.first_child_id == None {
                 this_node.first_child_id = this_node.last_child_id;
             }
         }
 
+        self.tree.notify(MutationRecord::Append {
+            parent_id: self.id,
+            child_id
+        });
         Some(())
     }
 
@@ -182,26 +376,97 @@ impl<'a, T: 'a> RefMut<'a, T> {
 
         // Update the new node's parent and next sibling.
         {
-            let new_child_node = self.tree.arena.get_mut(child_id)?;
+            let new_child_node = self.tree.node_mut(child_id)?;
             new_child_node.parent_id = Some(self.id);
             new_child_node.next_sibling_id = old_first_child_id;
         }
 
         // Update the old first child's prev sibling.
         if let Some(old_first_child_id) = old_first_child_id {
-            let old_first_child_node = self.tree.arena.get_mut(old_first_child_id)?;
+            let old_first_child_node = self.tree.node_mut(old_first_child_id)?;
             old_first_child_node.prev_sibling_id = Some(child_id);
         }
 
         // Update this node's first and last child.
         {
-            let this_node = self.tree.arena.get_mut(self.id)?;
+            let this_node = self.tree.node_mut(self.id)?;
             this_node.first_child_id = Some(child_id);
             if this_node.last_child_id == None {
                 this_node.last_child_id = this_node.first_child_id;
             }
         }
 
+        self.tree.notify(MutationRecord::Prepend {
+            parent_id: self.id,
+            child_id
+        });
+        Some(())
+    }
+
+    pub fn insert_id(&mut self, child_id: Id<T>, index: usize) -> Option<()> {
+        // Indices past the last child append, like `TLayoutNode::insert_child`.
+        let sibling_id = self.tree.get(self.id).children_ids_iter().nth(index);
+        match sibling_id {
+            Some(sibling_id) => self.tree.get_mut(sibling_id).insert_id_before(child_id),
+            None => self.append_id(child_id)
+        }
+    }
+
+    pub fn insert_id_before(&mut self, sibling_id: Id<T>) -> Option<()> {
+        let parent_id = self.parent_id()?;
+        let old_prev_sibling_id = self.prev_sibling_id();
+
+        // Update the new node's parent and siblings.
+        {
+            let new_sibling_node = self.tree.node_mut(sibling_id)?;
+            new_sibling_node.parent_id = Some(parent_id);
+            new_sibling_node.prev_sibling_id = old_prev_sibling_id;
+            new_sibling_node.next_sibling_id = Some(self.id);
+        }
+
+        // Update this node's prev sibling.
+        self.tree.node_mut(self.id)?.prev_sibling_id = Some(sibling_id);
+
+        // Update the old prev sibling's next sibling, or the parent's first child.
+        if let Some(old_prev_sibling_id) = old_prev_sibling_id {
+            self.tree.node_mut(old_prev_sibling_id)?.next_sibling_id = Some(sibling_id);
+        } else {
+            self.tree.node_mut(parent_id)?.first_child_id = Some(sibling_id);
+        }
+
+        self.tree.notify(MutationRecord::InsertBefore {
+            sibling_id: self.id,
+            child_id: sibling_id
+        });
+        Some(())
+    }
+
+    pub fn insert_id_after(&mut self, sibling_id: Id<T>) -> Option<()> {
+        let parent_id = self.parent_id()?;
+        let old_next_sibling_id = self.next_sibling_id();
+
+        // Update the new node's parent and siblings.
+        {
+            let new_sibling_node = self.tree.node_mut(sibling_id)?;
+            new_sibling_node.parent_id = Some(parent_id);
+            new_sibling_node.prev_sibling_id = Some(self.id);
+            new_sibling_node.next_sibling_id = old_next_sibling_id;
+        }
+
+        // Update this node's next sibling.
+        self.tree.node_mut(self.id)?.next_sibling_id = Some(sibling_id);
+
+        // Update the old next sibling's prev sibling, or the parent's last child.
+        if let Some(old_next_sibling_id) = old_next_sibling_id {
+            self.tree.node_mut(old_next_sibling_id)?.prev_sibling_id = Some(sibling_id);
+        } else {
+            self.tree.node_mut(parent_id)?.last_child_id = Some(sibling_id);
+        }
+
+        self.tree.notify(MutationRecord::InsertAfter {
+            sibling_id: self.id,
+            child_id: sibling_id
+        });
         Some(())
     }
 
@@ -211,7 +476,7 @@ impl<'a, T: 'a> RefMut<'a, T> {
 
         // Update this node's parent, prev and next siblings.
         {
-            let this_node = self.tree.arena.get_mut(self.id)?;
+            let this_node = self.tree.node_mut(self.id)?;
             this_node.parent_id = None;
             this_node.prev_sibling_id = None;
             this_node.next_sibling_id = None;
@@ -219,31 +484,150 @@ impl<'a, T: 'a> RefMut<'a, T> {
 
         // Link the old prev and next siblings together.
         if let Some(old_prev_sibling_id) = old_prev_sibling_id {
-            let old_prev_sibling_node = self.tree.arena.get_mut(old_prev_sibling_id)?;
+            let old_prev_sibling_node = self.tree.node_mut(old_prev_sibling_id)?;
             old_prev_sibling_node.next_sibling_id = old_next_sibling_id;
         }
 
         if let Some(old_next_sibling_id) = old_next_sibling_id {
-            let old_next_sibling_node = self.tree.arena.get_mut(old_next_sibling_id)?;
+            let old_next_sibling_node = self.tree.node_mut(old_next_sibling_id)?;
             old_next_sibling_node.prev_sibling_id = old_prev_sibling_id;
         }
 
         // Update the old parent node's first and last children.
-        let old_parent_node = self.tree.arena.get_mut(old_parent_id)?;
-        let old_parents_first_child_id = old_parent_node.first_child_id;
-        let old_parents_last_child_id = old_parent_node.last_child_id;
-
-        if old_parents_first_child_id == old_parents_last_child_id {
-            old_parent_node.first_child_id = None;
-            old_parent_node.last_child_id = None;
-        } else if old_parents_first_child_id == Some(self.id) {
-            old_parent_node.first_child_id = old_next_sibling_id;
-            old_parent_node.last_child_id = old_parents_last_child_id;
-        } else if old_parents_last_child_id == Some(self.id) {
-            old_parent_node.first_child_id = old_parents_first_child_id;
-            old_parent_node.last_child_id = old_prev_sibling_id;
+        {
+            let old_parent_node = self.tree.node_mut(old_parent_id)?;
+            let old_parents_first_child_id = old_parent_node.first_child_id;
+            let old_parents_last_child_id = old_parent_node.last_child_id;
+
+            if old_parents_first_child_id == old_parents_last_child_id {
+                old_parent_node.first_child_id = None;
+                old_parent_node.last_child_id = None;
+            } else if old_parents_first_child_id == Some(self.id) {
+                old_parent_node.first_child_id = old_next_sibling_id;
+                old_parent_node.last_child_id = old_parents_last_child_id;
+            } else if old_parents_last_child_id == Some(self.id) {
+                old_parent_node.first_child_id = old_parents_first_child_id;
+                old_parent_node.last_child_id = old_prev_sibling_id;
+            }
         }
 
+        self.tree.notify(MutationRecord::Detach {
+            parent_id: old_parent_id,
+            child_id: self.id,
+            prev_sibling_id: old_prev_sibling_id,
+            next_sibling_id: old_next_sibling_id
+        });
         Some(())
     }
+
+    pub fn walk_mut<F, G>(&mut self, mut enter: F, mut exit: G) -> bool
+    where
+        F: FnMut(WalkRefMut<T>) -> Walk,
+        G: FnMut(WalkRefMut<T>) -> Walk
+    {
+        let root_id = self.id;
+        let mut ancestor_ids = vec![];
+        let mut next_id = Some(root_id);
+
+        loop {
+            // Enter the next node, descending into its children unless told otherwise.
+            let current_id = match next_id.take() {
+                Some(id) => {
+                    let walk = enter(WalkRefMut::new(self.tree, id, &ancestor_ids));
+                    let first_child_id = self.tree.get(id).first_child_id();
+                    match (walk, first_child_id) {
+                        (Walk::Stop, _) => return false,
+                        (Walk::Continue, Some(first_child_id)) => {
+                            ancestor_ids.push(id);
+                            next_id = Some(first_child_id);
+                            continue;
+                        }
+                        (Walk::Continue, None) | (Walk::SkipChildren, _) => id
+                    }
+                }
+                None => ancestor_ids.pop().expect("Walked past the root")
+            };
+
+            // Exit the current node, then move on to its next sibling or parent.
+            let walk = exit(WalkRefMut::new(self.tree, current_id, &ancestor_ids));
+            if walk == Walk::Stop {
+                return false;
+            }
+            if current_id == root_id {
+                return true;
+            }
+            next_id = self.tree.get(current_id).next_sibling_id();
+        }
+    }
+
+    pub fn append_clone_of_with<F>(&mut self, other: &Ref<T>, mut f: F) -> Option<Id<T>>
+    where
+        F: FnMut(&T) -> T
+    {
+        let values = other
+            .descendants_depths_iter()
+            .map(|(depth, node)| (depth, node.try_into_value().map(&mut f)));
+        self.append_preorder(skip_missing_subtrees(values))
+    }
+
+    pub fn append_split_off(&mut self, other: &mut Tree<T>, id: Id<T>) -> Option<Id<T>> {
+        let values = other.take_subtree(id)?;
+        self.append_preorder(values)
+    }
+
+    // Values at depth 0 become children of this node, deeper ones go under the last
+    // value above them.
+    pub fn append_preorder<I>(&mut self, values: I) -> Option<Id<T>>
+    where
+        I: IntoIterator<Item = (usize, T)>
+    {
+        let mut parent_ids = vec![self.id];
+        let mut first_id = None;
+
+        for (depth, value) in values {
+            parent_ids.truncate(depth + 1);
+            let parent_id = *parent_ids.last()?;
+            let id = self.tree.alloc(value);
+            self.tree.get_mut(parent_id).append_id(id)?;
+            parent_ids.push(id);
+            first_id = first_id.or(Some(id));
+        }
+
+        first_id
+    }
+}
+
+impl<'a, T: 'a> RefMut<'a, T>
+where
+    T: Clone
+{
+    pub fn append_clone_of(&mut self, other: &Ref<T>) -> Option<Id<T>> {
+        self.append_clone_of_with(other, T::clone)
+    }
+
+    pub fn append_clone_of_id(&mut self, id: Id<T>) -> Option<Id<T>> {
+        let values: Vec<_> = self
+            .tree
+            .get(id)
+            .descendants_depths_iter()
+            .map(|(depth, node)| (depth, node.try_into_value().cloned()))
+            .collect();
+        self.append_preorder(skip_missing_subtrees(values))
+    }
+}
+
+// Descendants of a missing value would end up under the wrong parent, so the whole
+// subtree is left out.
+fn skip_missing_subtrees<I, V>(values: I) -> impl Iterator<Item = (usize, V)>
+where
+    I: IntoIterator<Item = (usize, Option<V>)>
+{
+    let mut skip_depth = None;
+    values.into_iter().filter_map(move |(depth, value)| {
+        if skip_depth.map_or(false, |skip_depth| depth > skip_depth) {
+            return None;
+        }
+        skip_depth = if value.is_none() { Some(depth) } else { None };
+        value.map(|value| (depth, value))
+    })
 }
@@ -31,11 +31,19 @@ impl<'a, T: 'a> RefMutPair<'a, T> {
     }
 
     pub fn try_values(&mut self) -> Option<(&mut T, &mut T)> {
+        self.tree.invalidate_aggregate(self.ids.0);
+        self.tree.invalidate_aggregate(self.ids.1);
+        self.tree.record_value(self.ids.0);
+        self.tree.record_value(self.ids.1);
         let (first, second) = self.tree.arena.get_mut_pair(self.ids.0, self.ids.1);
         Some((&mut first?.value, &mut second?.value))
     }
 
     pub fn try_into_values(self) -> Option<(&'a mut T, &'a mut T)> {
+        self.tree.invalidate_aggregate(self.ids.0);
+        self.tree.invalidate_aggregate(self.ids.1);
+        self.tree.record_value(self.ids.0);
+        self.tree.record_value(self.ids.1);
         let (first, second) = self.tree.arena.get_mut_pair(self.ids.0, self.ids.1);
         Some((&mut first?.value, &mut second?.value))
     }
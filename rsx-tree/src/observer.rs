@@ -0,0 +1,141 @@
+/*
+Copyright 2016 Mozilla
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+this file except in compliance with the License. You may obtain a copy of the
+License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed
+under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+CONDITIONS OF ANY KIND, either express or implied. See the License for the
+specific language governing permissions and limitations under the License.
+*/
+
+use std::fmt;
+use std::mem;
+
+use types::{Id, Tree};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct ObserverId(usize);
+
+#[derive(Debug, PartialEq)]
+pub enum MutationRecord<T> {
+    Append {
+        parent_id: Id<T>,
+        child_id: Id<T>
+    },
+    Prepend {
+        parent_id: Id<T>,
+        child_id: Id<T>
+    },
+    InsertBefore {
+        sibling_id: Id<T>,
+        child_id: Id<T>
+    },
+    InsertAfter {
+        sibling_id: Id<T>,
+        child_id: Id<T>
+    },
+    Detach {
+        parent_id: Id<T>,
+        child_id: Id<T>,
+        prev_sibling_id: Option<Id<T>>,
+        next_sibling_id: Option<Id<T>>
+    },
+    Reorder {
+        parent_id: Id<T>
+    },
+    ValueMut {
+        id: Id<T>
+    },
+    Remove {
+        id: Id<T>
+    }
+}
+
+impl<T> Copy for MutationRecord<T> {}
+
+impl<T> Clone for MutationRecord<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+pub type Observer<T> = Box<FnMut(&MutationRecord<T>, &Tree<T>)>;
+
+pub(crate) struct Observers<T> {
+    next_id: usize,
+    list: Vec<(ObserverId, Observer<T>)>
+}
+
+impl<T> Default for Observers<T> {
+    fn default() -> Self {
+        Observers {
+            next_id: 0,
+            list: vec![]
+        }
+    }
+}
+
+impl<T> fmt::Debug for Observers<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "Observers({})", self.list.len())
+    }
+}
+
+// Observers are bookkeeping, and never make two trees different.
+impl<T> PartialEq for Observers<T> {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl<T> Observers<T> {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
+    pub(crate) fn add(&mut self, observer: Observer<T>) -> ObserverId {
+        let id = ObserverId(self.next_id);
+        self.next_id += 1;
+        self.list.push((id, observer));
+        id
+    }
+
+    pub(crate) fn remove(&mut self, id: ObserverId) -> bool {
+        let len = self.list.len();
+        self.list.retain(|&(v, _)| v != id);
+        self.list.len() != len
+    }
+
+    pub(crate) fn notify(&mut self, record: &MutationRecord<T>, tree: &Tree<T>) {
+        for &mut (_, ref mut observer) in &mut self.list {
+            observer(record, tree);
+        }
+    }
+}
+
+impl<T> Tree<T> {
+    pub fn observe<F>(&mut self, observer: F) -> ObserverId
+    where
+        F: FnMut(&MutationRecord<T>, &Tree<T>) + 'static
+    {
+        self.observers.add(Box::new(observer))
+    }
+
+    pub fn unobserve(&mut self, id: ObserverId) -> bool {
+        self.observers.remove(id)
+    }
+
+    pub(crate) fn notify(&mut self, record: MutationRecord<T>) {
+        self.aggregates.invalidate(&record, self);
+
+        if self.observers.is_empty() {
+            return;
+        }
+
+        // Observers get to look at the tree, so they're moved out while running.
+        let mut observers = mem::replace(&mut self.observers, Observers::default());
+        observers.notify(&record, self);
+        self.observers = observers;
+    }
+}
@@ -0,0 +1,218 @@
+/*
+Copyright 2016 Mozilla
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+this file except in compliance with the License. You may obtain a copy of the
+License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed
+under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+CONDITIONS OF ANY KIND, either express or implied. See the License for the
+specific language governing permissions and limitations under the License.
+*/
+
+use std::collections::HashMap;
+
+use rayon::prelude::*;
+
+#[cfg(not(feature = "persistent-arena"))]
+use types::Node;
+use types::{Id, MutationRecord, Ref, RefMut, Tree};
+
+// A task owns the value of a node and the tasks of its children, so sibling subtrees
+// never share a reference and can be handed over to different threads.
+struct Task<'a, T: 'a> {
+    value: &'a mut T,
+    children: Vec<Task<'a, T>>
+}
+
+impl<'a, T> Task<'a, T>
+where
+    T: Send + 'a
+{
+    // Each value is taken out of the map once, following the links collected before
+    // the values were borrowed.
+    fn build(values: &mut HashMap<Id<T>, &'a mut T>, children_ids: &HashMap<Id<T>, Vec<Id<T>>>, id: Id<T>) -> Option<Self> {
+        let value = values.remove(&id)?;
+        let children = children_ids
+            .get(&id)
+            .map(|ids| {
+                ids.iter()
+                    .filter_map(|&id| Task::build(values, children_ids, id))
+                    .collect()
+            })
+            .unwrap_or_default();
+        Some(Task { value, children })
+    }
+
+    fn for_each<F>(self, f: &F)
+    where
+        F: Fn(&mut T) + Sync
+    {
+        f(self.value);
+        self.children
+            .into_par_iter()
+            .for_each(|child| child.for_each(f));
+    }
+
+    fn map<R, F>(self, f: &F) -> R
+    where
+        R: Send,
+        F: Fn(&mut T, Vec<R>) -> R + Sync
+    {
+        let results = self
+            .children
+            .into_par_iter()
+            .map(|child| child.map(f))
+            .collect();
+        f(self.value, results)
+    }
+}
+
+// Nodes left to visit: one on its own, and any number of whole subtrees.
+#[cfg(not(feature = "persistent-arena"))]
+type Pending<T> = (Option<Id<T>>, Vec<Id<T>>);
+
+impl<'a, T> Ref<'a, T>
+where
+    T: Send + Sync + 'a
+{
+    // Subtrees are split off following the links whenever there's a thread to take
+    // them, so the walk itself runs in parallel as well.
+    #[cfg(not(feature = "persistent-arena"))]
+    pub fn par_descendants(&self) -> impl ParallelIterator<Item = (Id<T>, &'a T)> {
+        let arena = &self.tree().arena;
+        let get = move |id| arena.get(id);
+        let pending: Pending<T> = (None, vec![self.id()]);
+
+        rayon::iter::split(pending, move |(node_id, mut subtree_ids)| {
+            if subtree_ids.len() > 1 {
+                let other_ids = subtree_ids.split_off(subtree_ids.len() / 2);
+                return ((node_id, subtree_ids), Some((None, other_ids)));
+            }
+            match (node_id, subtree_ids.pop()) {
+                (None, Some(id)) => {
+                    let child_ids = children_ids(&get, id);
+                    let rest = if child_ids.is_empty() {
+                        None
+                    } else {
+                        Some((None, child_ids))
+                    };
+                    ((Some(id), vec![]), rest)
+                }
+                (node_id, subtree_id) => ((node_id, subtree_id.into_iter().collect()), None)
+            }
+        })
+        .flat_map(move |(node_id, subtree_ids)| {
+            let mut values = vec![];
+            values.extend(node_id.and_then(|id| Some((id, &get(id)?.value))));
+            for id in subtree_ids {
+                descendants(&get, id, &mut values);
+            }
+            values
+        })
+    }
+
+    // Persistent arenas share entries through `Rc`, which can't be sent to another
+    // thread, so the values are gathered up front.
+    #[cfg(feature = "persistent-arena")]
+    pub fn par_descendants(&self) -> impl ParallelIterator<Item = (Id<T>, &'a T)> {
+        let values: Vec<_> = self
+            .descendants_refs_iter()
+            .filter_map(|node| Some((node.id(), node.try_into_value()?)))
+            .collect();
+        values.into_par_iter()
+    }
+}
+
+impl<'a, T> RefMut<'a, T>
+where
+    T: Send + 'a
+{
+    // Parents are visited before their children, siblings are visited in parallel.
+    pub fn par_for_each_mut<F>(&mut self, f: F) -> Option<()>
+    where
+        F: Fn(&mut T) + Sync
+    {
+        let ids = {
+            let (ids, task) = self.par_task()?;
+            task.for_each(&f);
+            ids
+        };
+        self.notify_values(ids);
+        Some(())
+    }
+
+    // Children are mapped before their parents, which receive the results in order.
+    pub fn par_map_subtrees<R, F>(&mut self, f: F) -> Option<R>
+    where
+        R: Send,
+        F: Fn(&mut T, Vec<R>) -> R + Sync
+    {
+        let (ids, result) = {
+            let (ids, task) = self.par_task()?;
+            (ids, task.map(&f))
+        };
+        self.notify_values(ids);
+        Some(result)
+    }
+
+    // Observers are told about every value in the subtree once all of them are done.
+    fn notify_values(&mut self, ids: Vec<Id<T>>) {
+        for id in ids {
+            self.tree.notify(MutationRecord::ValueMut { id });
+        }
+    }
+
+    fn par_task(&mut self) -> Option<(Vec<Id<T>>, Task<T>)> {
+        let (ids, children_ids) = {
+            let tree = &self.tree;
+            let ids: Vec<_> = tree.get(self.id).descendants_ids_iter().collect();
+            let children_ids: HashMap<_, Vec<_>> = ids
+                .iter()
+                .map(|&id| (id, tree.get(id).children_ids_iter().collect()))
+                .collect();
+            (ids, children_ids)
+        };
+        for &id in &ids {
+            self.tree.record_value(id);
+        }
+        let mut values = self
+            .tree
+            .arena
+            .get_mut_many(&ids)
+            .into_iter()
+            .map(|(id, node)| (id, &mut node.value))
+            .collect();
+        let task = Task::build(&mut values, &children_ids, self.id)?;
+        Some((ids, task))
+    }
+}
+
+#[cfg(not(feature = "persistent-arena"))]
+fn children_ids<'a, T, G>(get: &G, id: Id<T>) -> Vec<Id<T>>
+where
+    T: 'a,
+    G: Fn(Id<T>) -> Option<&'a Node<T>>
+{
+    let mut ids = vec![];
+    let mut next_id = get(id).and_then(|node| node.first_child_id);
+    while let Some(id) = next_id {
+        ids.push(id);
+        next_id = get(id).and_then(|node| node.next_sibling_id);
+    }
+    ids
+}
+
+#[cfg(not(feature = "persistent-arena"))]
+fn descendants<'a, T, G>(get: &G, id: Id<T>, values: &mut Vec<(Id<T>, &'a T)>)
+where
+    T: 'a,
+    G: Fn(Id<T>) -> Option<&'a Node<T>>
+{
+    let mut stack = vec![id];
+    while let Some(id) = stack.pop() {
+        if let Some(node) = get(id) {
+            values.push((id, &node.value));
+            stack.extend(children_ids(get, id).into_iter().rev());
+        }
+    }
+}
@@ -0,0 +1,156 @@
+/*
+Copyright 2016 Mozilla
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+this file except in compliance with the License. You may obtain a copy of the
+License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed
+under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+CONDITIONS OF ANY KIND, either express or implied. See the License for the
+specific language governing permissions and limitations under the License.
+*/
+
+use std::error::Error;
+use std::fmt;
+use std::num::ParseIntError;
+use std::ops::Deref;
+use std::str::FromStr;
+
+use types::{Id, Ref, Tree};
+
+// Child indices starting from the root, written as "/2/0". The root itself is "/".
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Default)]
+pub struct NodePath(Vec<usize>);
+
+impl NodePath {
+    pub fn root() -> Self {
+        NodePath::default()
+    }
+
+    pub fn is_root(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn push(&mut self, index: usize) {
+        self.0.push(index);
+    }
+
+    pub fn pop(&mut self) -> Option<usize> {
+        self.0.pop()
+    }
+
+    pub fn child(&self, index: usize) -> Self {
+        let mut path = self.clone();
+        path.push(index);
+        path
+    }
+
+    pub fn parent(&self) -> Option<Self> {
+        let (_, indices) = self.0.split_last()?;
+        Some(NodePath(indices.to_vec()))
+    }
+}
+
+impl From<Vec<usize>> for NodePath {
+    fn from(indices: Vec<usize>) -> Self {
+        NodePath(indices)
+    }
+}
+
+impl Into<Vec<usize>> for NodePath {
+    fn into(self) -> Vec<usize> {
+        self.0
+    }
+}
+
+impl Deref for NodePath {
+    type Target = [usize];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl fmt::Display for NodePath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        if self.is_root() {
+            return write!(f, "/");
+        }
+        for index in &self.0 {
+            write!(f, "/{}", index)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum ParseNodePathError {
+    MissingRoot,
+    EmptySegment,
+    Index(ParseIntError)
+}
+
+impl fmt::Display for ParseNodePathError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &ParseNodePathError::Index(ref error) => write!(f, "Invalid child index: {}", error),
+            &ParseNodePathError::MissingRoot | &ParseNodePathError::EmptySegment => f.write_str(self.description())
+        }
+    }
+}
+
+impl Error for ParseNodePathError {
+    fn description(&self) -> &str {
+        match self {
+            &ParseNodePathError::MissingRoot => "Node paths start with a `/`",
+            &ParseNodePathError::EmptySegment => "Node paths can't have empty segments",
+            &ParseNodePathError::Index(_) => "Invalid child index"
+        }
+    }
+}
+
+impl FromStr for NodePath {
+    type Err = ParseNodePathError;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        let string = string.trim();
+        if !string.starts_with('/') {
+            return Err(ParseNodePathError::MissingRoot);
+        }
+        if string == "/" {
+            return Ok(NodePath::root());
+        }
+        string[1..]
+            .split('/')
+            .map(|segment| match segment {
+                "" => Err(ParseNodePathError::EmptySegment),
+                _ => usize::from_str(segment).map_err(ParseNodePathError::Index)
+            })
+            .collect::<Result<_, _>>()
+            .map(NodePath)
+    }
+}
+
+impl<'a, T: 'a> Ref<'a, T> {
+    // Only nodes that are connected to the tree root have a path.
+    pub fn path(&self) -> Option<NodePath> {
+        let mut indices = vec![];
+        let mut node = *self;
+        while let Some(parent) = node.parent() {
+            indices.push(node.child_index()?);
+            node = parent;
+        }
+        if node.id() != self.tree().root() {
+            return None;
+        }
+        indices.reverse();
+        Some(NodePath(indices))
+    }
+}
+
+impl<T> Tree<T> {
+    pub fn resolve(&self, path: &NodePath) -> Option<Id<T>> {
+        path.iter().fold(Some(self.root), |id, &index| {
+            self.get(id?).children_ids_iter().nth(index)
+        })
+    }
+}
@@ -0,0 +1,124 @@
+/*
+Copyright 2016 Mozilla
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+this file except in compliance with the License. You may obtain a copy of the
+License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed
+under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+CONDITIONS OF ANY KIND, either express or implied. See the License for the
+specific language governing permissions and limitations under the License.
+*/
+
+use std::collections::HashSet;
+
+use types::{Id, MutationRecord, RefMut};
+
+// Ranges are runs of consecutive children of this node, from `first_id` to `last_id`
+// inclusive. Only the links around both ends of a run are changed when splicing it,
+// nodes inside the run are touched when their parent changes.
+impl<'a, T: 'a> RefMut<'a, T> {
+    pub fn children_range(&self, first_id: Id<T>, last_id: Id<T>) -> Option<Vec<Id<T>>> {
+        let first = self.tree.get(first_id);
+        if first.parent_id() != Some(self.id) {
+            return None;
+        }
+
+        let mut ids = vec![first_id];
+        let mut id = first_id;
+        while id != last_id {
+            id = self.tree.get(id).next_sibling_id()?;
+            ids.push(id);
+        }
+        Some(ids)
+    }
+
+    pub fn detach_range(&mut self, first_id: Id<T>, last_id: Id<T>) -> Option<Vec<Id<T>>> {
+        let ids = self.children_range(first_id, last_id)?;
+        let (prev_sibling_id, next_sibling_id) = self.unlink_range(first_id, last_id)?;
+
+        // Splicing the run out is O(1), but detaching it isn't: detached nodes can't
+        // keep their parent or siblings, so every node in the run is reset, which is
+        // O(n) in its length, same as collecting the ids above.
+        for &id in &ids {
+            let node = self.tree.node_mut(id)?;
+            node.parent_id = None;
+            node.prev_sibling_id = None;
+            node.next_sibling_id = None;
+        }
+
+        self.notify_range_detach(&ids, prev_sibling_id, next_sibling_id);
+        Some(ids)
+    }
+
+    pub fn move_range_after(&mut self, first_id: Id<T>, last_id: Id<T>, anchor_id: Id<T>) -> Option<()> {
+        let ids = self.children_range(first_id, last_id)?;
+        let new_parent_id = self.tree.get(anchor_id).parent_id()?;
+
+        // The anchor can't be moved along, nor sit inside one of the moved subtrees.
+        {
+            let moved: HashSet<_> = ids.iter().collect();
+            let anchor = self.tree.get(anchor_id);
+            if moved.contains(&anchor_id) || anchor.ancestors_ids_iter().any(|id| moved.contains(&id)) {
+                return None;
+            }
+        }
+
+        let (prev_sibling_id, next_sibling_id) = self.unlink_range(first_id, last_id)?;
+        let anchor_next_sibling_id = self.tree.get(anchor_id).next_sibling_id();
+
+        // Link the run in between the anchor and its old next sibling.
+        self.tree.node_mut(anchor_id)?.next_sibling_id = Some(first_id);
+        self.tree.node_mut(first_id)?.prev_sibling_id = Some(anchor_id);
+        self.tree.node_mut(last_id)?.next_sibling_id = anchor_next_sibling_id;
+        match anchor_next_sibling_id {
+            Some(id) => self.tree.node_mut(id)?.prev_sibling_id = Some(last_id),
+            None => self.tree.node_mut(new_parent_id)?.last_child_id = Some(last_id)
+        }
+
+        if new_parent_id != self.id {
+            for &id in &ids {
+                self.tree.node_mut(id)?.parent_id = Some(new_parent_id);
+            }
+        }
+
+        self.notify_range_detach(&ids, prev_sibling_id, next_sibling_id);
+        let mut sibling_id = anchor_id;
+        for &child_id in &ids {
+            self.tree.notify(MutationRecord::InsertAfter {
+                sibling_id,
+                child_id
+            });
+            sibling_id = child_id;
+        }
+        Some(())
+    }
+
+    // Links the siblings on either side of a run together, returning them.
+    fn unlink_range(&mut self, first_id: Id<T>, last_id: Id<T>) -> Option<(Option<Id<T>>, Option<Id<T>>)> {
+        let prev_sibling_id = self.tree.get(first_id).prev_sibling_id();
+        let next_sibling_id = self.tree.get(last_id).next_sibling_id();
+
+        match prev_sibling_id {
+            Some(id) => self.tree.node_mut(id)?.next_sibling_id = next_sibling_id,
+            None => self.tree.node_mut(self.id)?.first_child_id = next_sibling_id
+        }
+        match next_sibling_id {
+            Some(id) => self.tree.node_mut(id)?.prev_sibling_id = prev_sibling_id,
+            None => self.tree.node_mut(self.id)?.last_child_id = prev_sibling_id
+        }
+
+        Some((prev_sibling_id, next_sibling_id))
+    }
+
+    // Observers see the run leave as if each node was detached in turn, front to back.
+    fn notify_range_detach(&mut self, ids: &[Id<T>], prev_sibling_id: Option<Id<T>>, next_sibling_id: Option<Id<T>>) {
+        for (index, &child_id) in ids.iter().enumerate() {
+            self.tree.notify(MutationRecord::Detach {
+                parent_id: self.id,
+                child_id,
+                prev_sibling_id,
+                next_sibling_id: ids.get(index + 1).cloned().or(next_sibling_id)
+            });
+        }
+    }
+}
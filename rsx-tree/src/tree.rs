@@ -9,20 +9,35 @@ CONDITIONS OF ANY KIND, either express or implied. See the License for the
 specific language governing permissions and limitations under the License.
 */
 
-#[cfg(all(not(feature = "vec-arena"), not(feature = "hashmap-arena")))]
+#[cfg(all(
+    not(feature = "vec-arena"),
+    not(feature = "hashmap-arena"),
+    not(feature = "persistent-arena")
+))]
 use rsx_arena::types::VecArena as Arena;
 
 #[cfg(feature = "hashmap-arena")]
 use rsx_arena::types::HashmapArena as Arena;
+#[cfg(feature = "persistent-arena")]
+use rsx_arena::types::PersistentArena as Arena;
 #[cfg(feature = "vec-arena")]
 use rsx_arena::types::VecArena as Arena;
 
-use types::{Id, IdPair, Node, Ref, RefMut, RefMutPair};
+use std::collections::HashSet;
+
+use aggregate::Aggregates;
+use history::History;
+use observer::Observers;
+use types::{Id, IdPair, MutationRecord, Node, Ref, RefMut, RefMutPair};
 
 #[derive(Debug, PartialEq)]
 pub struct Tree<T> {
     pub(crate) arena: Arena<Node<T>>,
-    pub(crate) root: Id<T>
+    pub(crate) root: Id<T>,
+    pub(crate) observers: Observers<T>,
+    pub(crate) history: History<T>,
+    pub(crate) aggregates: Aggregates<T>,
+    pub(crate) pinned: HashSet<Id<T>>
 }
 
 impl<T> Tree<T> {
@@ -32,7 +47,18 @@ impl<T> Tree<T> {
     {
         let mut arena = Arena::new();
         let root = arena.alloc(Node::new(U::into(root)));
-        Tree { arena, root }
+        let observers = Observers::default();
+        let history = History::default();
+        let aggregates = Aggregates::default();
+        let pinned = HashSet::new();
+        Tree {
+            arena,
+            root,
+            observers,
+            history,
+            aggregates,
+            pinned
+        }
     }
 
     pub fn root(&self) -> Id<T> {
@@ -57,4 +83,120 @@ impl<T> Tree<T> {
     pub fn get_mut_pair(&mut self, ids: IdPair<T>) -> RefMutPair<T> {
         RefMutPair::new(self, ids)
     }
+
+    pub fn swap_nodes(&mut self, a: Id<T>, b: Id<T>) -> Option<()> {
+        if a == b {
+            return Some(());
+        }
+
+        // Nodes can only trade places when neither contains the other.
+        if self.get(a).is_ancestor_of(b) || self.get(b).is_ancestor_of(a) {
+            return None;
+        }
+
+        self.get(a).parent_id()?;
+        let b_parent_id = self.get(b).parent_id()?;
+        let (a_next_sibling_id, b_next_sibling_id) = (self.get(a).next_sibling_id(), self.get(b).next_sibling_id());
+
+        // Adjacent siblings only need the first one moved after the second.
+        if a_next_sibling_id == Some(b) {
+            self.get_mut(a).detach_own_id()?;
+            return self.get_mut(b).insert_id_after(a);
+        }
+        if b_next_sibling_id == Some(a) {
+            self.get_mut(b).detach_own_id()?;
+            return self.get_mut(a).insert_id_after(b);
+        }
+
+        self.get_mut(b).detach_own_id()?;
+        self.get_mut(a).insert_id_before(b)?;
+        self.get_mut(a).detach_own_id()?;
+
+        match b_next_sibling_id {
+            Some(b_next_sibling_id) => self.get_mut(b_next_sibling_id).insert_id_before(a),
+            None => self.get_mut(b_parent_id).append_id(a)
+        }
+    }
+
+    pub fn clone_subtree_with<F>(&self, id: Id<T>, mut f: F) -> Tree<T>
+    where
+        F: FnMut(&T) -> T
+    {
+        let node = self.get(id);
+        let mut tree = Tree::new(f(node.try_value().expect("Node deallocated")));
+        {
+            let root_id = tree.root();
+            let mut root = tree.get_mut(root_id);
+            for child in node.children_refs_iter() {
+                root.append_clone_of_with(&child, &mut f);
+            }
+        }
+        tree
+    }
+
+    pub fn split_off(&mut self, id: Id<T>) -> Option<Tree<T>> {
+        let mut values = self.take_subtree(id)?.into_iter();
+        let (_, value) = values.next()?;
+        let mut tree = Tree::new(value);
+        {
+            let root_id = tree.root();
+            tree.get_mut(root_id)
+                .append_preorder(values.map(|(depth, value)| (depth - 1, value)));
+        }
+        Some(tree)
+    }
+
+    // The root can't be taken out of its own tree.
+    pub(crate) fn take_subtree(&mut self, id: Id<T>) -> Option<Vec<(usize, T)>> {
+        if id == self.root {
+            return None;
+        }
+        self.arena.get(id)?;
+
+        self.get_mut(id).detach();
+
+        let ids: Vec<_> = self
+            .get(id)
+            .descendants_depths_iter()
+            .map(|(depth, node)| (depth, node.id()))
+            .collect();
+        let mut values = Vec::with_capacity(ids.len());
+        for (depth, id) in ids {
+            if let Some(node) = self.dealloc(id) {
+                values.push((depth, node.value));
+            }
+        }
+        Some(values)
+    }
+
+    pub(crate) fn dealloc(&mut self, id: Id<T>) -> Option<Node<T>> {
+        self.arena.get(id)?;
+        self.notify(MutationRecord::Remove { id });
+        self.record_dealloc(id);
+        self.pinned.remove(&id);
+        self.arena.dealloc(id)
+    }
+}
+
+impl<T> Tree<T>
+where
+    T: Clone
+{
+    pub fn clone_subtree(&self, id: Id<T>) -> Tree<T> {
+        self.clone_subtree_with(id, T::clone)
+    }
+
+    // Snapshots share all nodes with this tree, and ids stay valid in both. Either side
+    // copies a node the first time it's mutated, which leaves the other one untouched.
+    #[cfg(feature = "persistent-arena")]
+    pub fn snapshot(&self) -> Tree<T> {
+        Tree {
+            arena: self.arena.snapshot(),
+            root: self.root,
+            observers: Observers::default(),
+            history: History::default(),
+            aggregates: Aggregates::default(),
+            pinned: self.pinned.clone()
+        }
+    }
 }
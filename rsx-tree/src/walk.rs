@@ -0,0 +1,114 @@
+/*
+Copyright 2016 Mozilla
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+this file except in compliance with the License. You may obtain a copy of the
+License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed
+under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+CONDITIONS OF ANY KIND, either express or implied. See the License for the
+specific language governing permissions and limitations under the License.
+*/
+
+use types::{Id, Ref, Tree};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Walk {
+    Continue,
+    SkipChildren,
+    Stop
+}
+
+#[derive(Debug)]
+pub struct WalkRefMut<'a, T: 'a> {
+    tree: &'a mut Tree<T>,
+    id: Id<T>,
+    ancestor_ids: &'a [Id<T>]
+}
+
+impl<'a, T: 'a> WalkRefMut<'a, T> {
+    pub(crate) fn new(tree: &'a mut Tree<T>, id: Id<T>, ancestor_ids: &'a [Id<T>]) -> Self {
+        WalkRefMut {
+            tree,
+            id,
+            ancestor_ids
+        }
+    }
+
+    pub fn id(&self) -> Id<T> {
+        self.id
+    }
+
+    pub fn depth(&self) -> usize {
+        self.ancestor_ids.len()
+    }
+
+    pub fn ancestor_ids(&self) -> &[Id<T>] {
+        self.ancestor_ids
+    }
+
+    pub fn try_value(&self) -> Option<&T> {
+        Some(&self.tree.arena.get(self.id)?.value)
+    }
+
+    pub fn try_value_mut(&mut self) -> Option<&mut T> {
+        self.tree.invalidate_aggregate(self.id);
+        self.tree.record_value(self.id);
+        Some(&mut self.tree.arena.get_mut(self.id)?.value)
+    }
+
+    pub fn try_into_value(self) -> Option<&'a mut T> {
+        self.tree.invalidate_aggregate(self.id);
+        self.tree.record_value(self.id);
+        Some(&mut self.tree.arena.get_mut(self.id)?.value)
+    }
+
+    pub fn parent(&self) -> Option<Ref<T>> {
+        let id = *self.ancestor_ids.last()?;
+        Some(self.tree.get(id))
+    }
+
+    pub fn ancestors<'b>(&'b self) -> impl Iterator<Item = Ref<'b, T>> + 'b {
+        let tree: &Tree<T> = self.tree;
+        self.ancestor_ids.iter().rev().map(move |&id| tree.get(id))
+    }
+
+    pub fn try_values_with_parent(&mut self) -> Option<(&mut T, Option<&mut T>)> {
+        match self.ancestor_ids.last() {
+            Some(&parent_id) => {
+                self.tree.invalidate_aggregate(self.id);
+                self.tree.invalidate_aggregate(parent_id);
+                self.tree.record_value(self.id);
+                self.tree.record_value(parent_id);
+                let (this_node, parent_node) = self.tree.arena.get_mut_pair(self.id, parent_id);
+                Some((&mut this_node?.value, Some(&mut parent_node?.value)))
+            }
+            None => Some((self.try_value_mut()?, None))
+        }
+    }
+
+    // Ancestors never alias the node being visited, so the arena can hand all of them
+    // out at once.
+    pub fn try_values_with_ancestors(&mut self) -> Option<(&mut T, Vec<&T>)> {
+        let id = self.id;
+        let ancestor_ids = self.ancestor_ids;
+        self.tree.invalidate_aggregate(id);
+        self.tree.record_value(id);
+
+        let mut ids = ancestor_ids.to_vec();
+        ids.push(id);
+        let mut nodes = self.tree.arena.get_mut_many(&ids);
+        let mut take = |id| {
+            let index = nodes.iter().position(|&(node_id, _)| node_id == id)?;
+            Some(nodes.swap_remove(index).1)
+        };
+
+        let this_node = take(id)?;
+        let ancestors = ancestor_ids
+            .iter()
+            .rev()
+            .filter_map(|&id| take(id))
+            .map(|node| &node.value)
+            .collect();
+        Some((&mut this_node.value, ancestors))
+    }
+}
@@ -0,0 +1,825 @@
+/*
+Copyright 2016 Mozilla
+Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+this file except in compliance with the License. You may obtain a copy of the
+License at http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software distributed
+under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+CONDITIONS OF ANY KIND, either express or implied. See the License for the
+specific language governing permissions and limitations under the License.
+*/
+
+#[cfg(feature = "rayon")]
+extern crate rayon;
+extern crate rsx_tree;
+
+use std::cell::RefCell;
+use std::cmp::Reverse;
+use std::rc::Rc;
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+use rsx_tree::types::*;
+
+fn build_tree() -> Tree<String> {
+    let mut tree = Tree::new("root".to_string());
+    {
+        let root_id = tree.root();
+        let mut root = tree.get_mut(root_id);
+        {
+            let mut a = root.append("a".to_string());
+            a.append("a1".to_string());
+            a.append("a2".to_string());
+        }
+        {
+            let mut b = root.append("b".to_string());
+            b.append("b1".to_string());
+        }
+        root.append("c".to_string());
+    }
+    tree
+}
+
+fn values(tree: &Tree<String>) -> Vec<&str> {
+    tree.get(tree.root()).descendants_values_iter().map(|v| v.as_str()).collect()
+}
+
+#[test]
+fn test_walk_mut() {
+    let mut tree = build_tree();
+    let root_id = tree.root();
+    let mut log = vec![];
+
+    let completed = tree.get_mut(root_id).walk_mut(
+        |mut node| {
+            let depth = node.depth();
+            let value = node.try_value_mut().unwrap();
+            value.push_str(&format!("@{}", depth));
+            Walk::Continue
+        },
+        |mut node| {
+            let (value, ancestors) = node.try_values_with_ancestors().unwrap();
+            log.push(format!("{} < {}", value, ancestors.iter().map(|v| v.as_str()).collect::<Vec<_>>().join(" < ")));
+            Walk::Continue
+        }
+    );
+
+    assert!(completed);
+    assert_eq!(values(&tree), vec!["root@0", "a@1", "a1@2", "a2@2", "b@1", "b1@2", "c@1"]);
+    assert_eq!(
+        log,
+        vec![
+            "a1@2 < a@1 < root@0",
+            "a2@2 < a@1 < root@0",
+            "a@1 < root@0",
+            "b1@2 < b@1 < root@0",
+            "b@1 < root@0",
+            "c@1 < root@0",
+            "root@0 < ",
+        ]
+    );
+}
+
+#[test]
+fn test_walk_mut_skip_and_stop() {
+    let mut tree = build_tree();
+    let root_id = tree.root();
+    let mut entered = vec![];
+
+    let completed = tree.get_mut(root_id).walk_mut(
+        |node| {
+            let value = node.try_value().unwrap().clone();
+            entered.push(value.clone());
+            match value.as_str() {
+                "a" => Walk::SkipChildren,
+                "b1" => Walk::Stop,
+                _ => Walk::Continue
+            }
+        },
+        |_| Walk::Continue
+    );
+
+    assert!(!completed);
+    assert_eq!(entered, vec!["root", "a", "b", "b1"]);
+}
+
+#[test]
+fn test_clone_subtree() {
+    let mut tree = build_tree();
+    let a_id = tree.get(tree.root()).first_child_id().unwrap();
+
+    let clone = tree.clone_subtree(a_id);
+    assert_eq!(values(&clone), vec!["a", "a1", "a2"]);
+
+    let c_id = tree.get(tree.root()).last_child_id().unwrap();
+    let clone_id = tree.get_mut(c_id).append_clone_of_id(a_id).unwrap();
+    assert_eq!(tree.get(clone_id).parent_id(), Some(c_id));
+    assert_eq!(values(&tree), vec!["root", "a", "a1", "a2", "b", "b1", "c", "a", "a1", "a2"]);
+
+    let mut other = Tree::new("other".to_string());
+    let other_root_id = other.root();
+    other.get_mut(other_root_id).append_clone_of(&clone.get(clone.root()));
+    assert_eq!(values(&other), vec!["other", "a", "a1", "a2"]);
+}
+
+#[test]
+fn test_split_off() {
+    let mut tree = build_tree();
+    let a_id = tree.get(tree.root()).first_child_id().unwrap();
+    let a1_id = tree.get(a_id).first_child_id().unwrap();
+
+    let split = tree.split_off(a_id).unwrap();
+
+    assert_eq!(values(&tree), vec!["root", "b", "b1", "c"]);
+    assert_eq!(values(&split), vec!["a", "a1", "a2"]);
+    assert_eq!(tree.get(a_id).try_value(), None);
+    assert_eq!(tree.get(a1_id).try_value(), None);
+    assert_eq!(split.get(split.root()).parent_id(), None);
+
+    let root_id = tree.root();
+    assert_eq!(tree.split_off(root_id), None);
+    assert_eq!(tree.split_off(a_id), None);
+    assert_eq!(values(&tree), vec!["root", "b", "b1", "c"]);
+}
+
+#[test]
+fn test_unwrap_and_wrap() {
+    let mut tree = build_tree();
+    let a_id = tree.get(tree.root()).first_child_id().unwrap();
+    let a2_id = tree.get(a_id).last_child_id().unwrap();
+
+    assert_eq!(tree.get(a2_id).child_index(), Some(1));
+    assert_eq!(tree.get_mut(a_id).unwrap_node(), Some("a".to_string()));
+    assert_eq!(values(&tree), vec!["root", "a1", "a2", "b", "b1", "c"]);
+    assert_eq!(tree.get(a2_id).parent_id(), Some(tree.root()));
+    assert_eq!(tree.get(a2_id).child_index(), Some(1));
+    assert_eq!(tree.get(a_id).try_value(), None);
+
+    let wrapper_id = tree.get_mut(a2_id).wrap("w".to_string()).id();
+    assert_eq!(values(&tree), vec!["root", "a1", "w", "a2", "b", "b1", "c"]);
+    assert_eq!(tree.get(wrapper_id).child_index(), Some(1));
+
+    let root_id = tree.root();
+    assert_eq!(tree.get_mut(root_id).unwrap_node(), None);
+    tree.get_mut(root_id).wrap("top".to_string());
+    assert_eq!(values(&tree)[..2].to_vec(), vec!["top", "root"]);
+}
+
+#[test]
+fn test_replace_swap_and_move() {
+    let mut tree = build_tree();
+    let root_id = tree.root();
+    let a_id = tree.get(root_id).first_child_id().unwrap();
+    let a1_id = tree.get(a_id).first_child_id().unwrap();
+    let b_id = tree.get(a_id).next_sibling_id().unwrap();
+    let b1_id = tree.get(b_id).first_child_id().unwrap();
+    let c_id = tree.get(root_id).last_child_id().unwrap();
+
+    assert_eq!(tree.get_mut(c_id).replace_with("d".to_string()), "c");
+    assert_eq!(values(&tree), vec!["root", "a", "a1", "a2", "b", "b1", "d"]);
+
+    assert_eq!(tree.swap_nodes(a_id, b_id), Some(()));
+    assert_eq!(values(&tree), vec!["root", "b", "b1", "a", "a1", "a2", "d"]);
+    assert_eq!(tree.swap_nodes(a1_id, b_id), Some(()));
+    assert_eq!(values(&tree), vec!["root", "a1", "a", "b", "b1", "a2", "d"]);
+    assert_eq!(tree.swap_nodes(a_id, b1_id), None);
+
+    assert_eq!(tree.get_mut(c_id).move_to(a_id, 0), Some(()));
+    assert_eq!(values(&tree), vec!["root", "a1", "a", "d", "b", "b1", "a2"]);
+    assert_eq!(tree.get_mut(a1_id).move_to(a_id, 10), Some(()));
+    assert_eq!(values(&tree), vec!["root", "a", "d", "b", "b1", "a2", "a1"]);
+    assert_eq!(tree.get_mut(a_id).move_to(b1_id, 0), None);
+
+    assert_eq!(tree.get_mut(b_id).replace_with_subtree(a1_id), Some(()));
+    assert_eq!(values(&tree), vec!["root", "a", "d", "a1", "a2"]);
+    assert_eq!(tree.get(b_id).parent_id(), None);
+    assert_eq!(tree.get_mut(a1_id).replace_with_subtree(a_id), None);
+
+    let e_id = tree.get_mut(root_id).append("e".to_string()).id();
+    tree.get_mut(e_id).unwrap_node();
+    assert_eq!(tree.get_mut(a_id).move_to(e_id, 0), None);
+    assert_eq!(tree.get(a_id).parent_id(), Some(root_id));
+    assert_eq!(values(&tree), vec!["root", "a", "d", "a1", "a2"]);
+}
+
+#[test]
+fn test_reorder_children() {
+    let mut tree = build_tree();
+    let root_id = tree.root();
+    let a_id = tree.get(root_id).first_child_id().unwrap();
+    let b_id = tree.get(a_id).next_sibling_id().unwrap();
+    let c_id = tree.get(root_id).last_child_id().unwrap();
+
+    assert_eq!(tree.get_mut(root_id).reverse_children(), Some(()));
+    assert_eq!(values(&tree), vec!["root", "c", "b", "b1", "a", "a1", "a2"]);
+
+    assert_eq!(tree.get_mut(root_id).sort_children_by(|a, b| a.cmp(b)), Some(()));
+    assert_eq!(values(&tree), vec!["root", "a", "a1", "a2", "b", "b1", "c"]);
+
+    assert_eq!(tree.get_mut(a_id).sort_children_by_key(|v| Reverse(v.clone())), Some(()));
+    assert_eq!(values(&tree), vec!["root", "a", "a2", "a1", "b", "b1", "c"]);
+
+    assert_eq!(tree.get_mut(root_id).reorder_children(&[b_id, c_id, a_id]), Some(()));
+    assert_eq!(values(&tree), vec!["root", "b", "b1", "c", "a", "a2", "a1"]);
+    assert_eq!(tree.get(root_id).last_child_id(), Some(a_id));
+    assert_eq!(tree.get(a_id).prev_sibling_id(), Some(c_id));
+
+    assert_eq!(tree.get_mut(root_id).reorder_children(&[b_id, c_id]), None);
+    assert_eq!(tree.get_mut(root_id).reorder_children(&[b_id, c_id, c_id]), None);
+}
+
+fn shape(tree: &Tree<String>) -> Vec<(usize, &str)> {
+    tree.get(tree.root())
+        .descendants_depths_iter()
+        .map(|(depth, node)| (depth, node.try_into_value().unwrap().as_str()))
+        .collect()
+}
+
+#[test]
+fn test_diff() {
+    let old = build_tree();
+
+    let mut new = build_tree();
+    {
+        let root_id = new.root();
+        let a_id = new.get(root_id).first_child_id().unwrap();
+        let c_id = new.get(root_id).last_child_id().unwrap();
+        let a2_id = new.get(a_id).last_child_id().unwrap();
+        new.get_mut(c_id).move_to(root_id, 0);
+        new.get_mut(a2_id).replace_with("a3".to_string());
+        new.get_mut(a_id).append("a4".to_string());
+        new.get_mut(c_id).append("c1".to_string());
+        let b_id = new.get(a_id).next_sibling_id().unwrap();
+        new.split_off(b_id);
+    }
+    assert_eq!(values(&new), vec!["root", "c", "c1", "a", "a1", "a3", "a4"]);
+
+    let edits = old.diff(&new);
+    assert_eq!(edits.len(), 5);
+    assert_eq!(edits[0], Edit::Delete { path: NodePath::from(vec![1]) });
+    assert_eq!(edits[1], Edit::Move { from: NodePath::from(vec![0]), to: NodePath::from(vec![1]) });
+    assert_eq!(edits[4], Edit::Update { path: NodePath::from(vec![1, 1]), value: "a3".to_string() });
+
+    let mut patched = build_tree();
+    assert_eq!(patched.apply_edits(&edits), Some(()));
+    assert_eq!(shape(&patched), shape(&new));
+
+    assert_eq!(new.diff(&new), vec![]);
+
+    let mut reverted = new.clone_subtree(new.root());
+    assert_eq!(reverted.apply_edits(&new.diff(&old)), Some(()));
+    assert_eq!(shape(&reverted), shape(&old));
+}
+
+#[test]
+fn test_observe() {
+    let mut tree = build_tree();
+    let root_id = tree.root();
+    let a_id = tree.get(root_id).first_child_id().unwrap();
+    let b_id = tree.get(a_id).next_sibling_id().unwrap();
+    let c_id = tree.get(root_id).last_child_id().unwrap();
+
+    let records = Rc::new(RefCell::new(vec![]));
+    let observer_id = {
+        let records = records.clone();
+        tree.observe(move |record, tree| {
+            let value = match *record {
+                MutationRecord::ValueMut { id } => tree.get(id).try_value().cloned(),
+                _ => None
+            };
+            records.borrow_mut().push((*record, value));
+        })
+    };
+
+    tree.get_mut(a_id).try_value_mut().unwrap().push('?');
+    tree.get_mut(c_id).update(|v| {
+        v.push('!');
+        Some(())
+    });
+    tree.get_mut(c_id).update(|_| None::<()>);
+    tree.get_mut(c_id).move_to(root_id, 0);
+    tree.get_mut(root_id).reverse_children();
+    tree.get_mut(b_id).unwrap_node();
+
+    let b1_id = tree.get(root_id).first_child_id().unwrap();
+    assert_eq!(
+        *records.borrow(),
+        vec![
+            (MutationRecord::ValueMut { id: a_id }, Some("a".to_string())),
+            (MutationRecord::ValueMut { id: c_id }, Some("c!".to_string())),
+            (
+                MutationRecord::Detach {
+                    parent_id: root_id,
+                    child_id: c_id,
+                    prev_sibling_id: Some(b_id),
+                    next_sibling_id: None
+                },
+                None
+            ),
+            (MutationRecord::InsertBefore { sibling_id: a_id, child_id: c_id }, None),
+            (MutationRecord::Reorder { parent_id: root_id }, None),
+            (
+                MutationRecord::Detach {
+                    parent_id: b_id,
+                    child_id: b1_id,
+                    prev_sibling_id: None,
+                    next_sibling_id: None
+                },
+                None
+            ),
+            (MutationRecord::InsertBefore { sibling_id: b_id, child_id: b1_id }, None),
+            (
+                MutationRecord::Detach {
+                    parent_id: root_id,
+                    child_id: b_id,
+                    prev_sibling_id: Some(b1_id),
+                    next_sibling_id: Some(a_id)
+                },
+                None
+            ),
+            (MutationRecord::Remove { id: b_id }, None),
+        ]
+    );
+
+    assert!(tree.unobserve(observer_id));
+    assert!(!tree.unobserve(observer_id));
+    tree.get_mut(root_id).append("d".to_string());
+    assert_eq!(records.borrow().len(), 9);
+}
+
+#[test]
+fn test_undo_redo() {
+    let mut tree = build_tree();
+    let root_id = tree.root();
+    let a_id = tree.get(root_id).first_child_id().unwrap();
+    let b_id = tree.get(a_id).next_sibling_id().unwrap();
+    let c_id = tree.get(root_id).last_child_id().unwrap();
+    let original = values(&tree).join(" ");
+
+    tree.begin();
+    tree.get_mut(c_id).move_to(root_id, 0);
+    tree.get_mut(a_id).set_value("A".to_string());
+    tree.begin();
+    tree.get_mut(b_id).append("d".to_string());
+    assert!(tree.commit());
+    assert!(tree.is_recording());
+    assert!(tree.commit());
+    assert!(!tree.commit());
+
+    let edited = "root c A a1 a2 b b1 d";
+    assert_eq!(values(&tree).join(" "), edited);
+    assert!(tree.can_undo());
+    assert!(tree.undo());
+    assert_eq!(values(&tree).join(" "), original);
+    assert!(!tree.undo());
+    assert!(tree.redo());
+    assert_eq!(values(&tree).join(" "), edited);
+    assert!(!tree.can_redo());
+
+    tree.begin();
+    tree.get_mut(root_id).reverse_children();
+    tree.get_mut(root_id).wrap("top".to_string());
+    assert_ne!(tree.root(), root_id);
+    assert!(tree.rollback());
+    assert_eq!(tree.root(), root_id);
+    assert_eq!(values(&tree).join(" "), edited);
+    assert!(tree.undo());
+    assert_eq!(values(&tree).join(" "), original);
+
+    tree.begin();
+    tree.get_mut(a_id).replace_with("x".to_string());
+    tree.get_mut(b_id).try_value_mut().unwrap().push('!');
+    let split = tree.split_off(c_id).unwrap();
+    assert_eq!(values(&split), vec!["c"]);
+    assert!(tree.commit());
+    assert_eq!(values(&tree).join(" "), "root x a1 a2 b! b1");
+    assert!(tree.undo());
+    assert_eq!(values(&tree).join(" "), original);
+    assert!(tree.redo());
+    assert_eq!(values(&tree).join(" "), "root x a1 a2 b! b1");
+    assert!(tree.undo());
+
+    tree.begin();
+    tree.get_mut(b_id).append("d".to_string());
+    let d_id = tree.get(b_id).last_child_id().unwrap();
+    tree.get_mut(d_id).replace_with("e".to_string());
+    assert!(tree.rollback());
+    assert_eq!(values(&tree).join(" "), original);
+
+    // Nodes deallocated outside of a transaction take the changes to them out of the
+    // history, along with everything before.
+    tree.begin();
+    tree.get_mut(b_id).set_value("B".to_string());
+    assert!(tree.commit());
+    tree.begin();
+    tree.get_mut(c_id).set_value("C".to_string());
+    assert!(tree.commit());
+    tree.split_off(b_id);
+    assert!(tree.undo());
+    assert_eq!(values(&tree).join(" "), "root a a1 a2 c");
+    assert!(!tree.can_undo());
+
+    // Links changed outside of a transaction take the changes relying on them out of
+    // the history too, while values can still be put back.
+    let a1_id = tree.get(a_id).first_child_id().unwrap();
+    tree.begin();
+    tree.get_mut(c_id).move_to(a_id, 0);
+    assert!(tree.commit());
+    tree.begin();
+    tree.get_mut(a1_id).set_value("A1".to_string());
+    assert!(tree.commit());
+    tree.get_mut(c_id).move_to(root_id, 1);
+    assert!(tree.undo());
+    assert_eq!(values(&tree).join(" "), "root a a1 a2 c");
+    assert!(!tree.can_undo());
+
+    tree.begin();
+    tree.get_mut(root_id).walk_mut(
+        |mut node| {
+            node.try_values_with_parent().unwrap().0.make_ascii_uppercase();
+            Walk::Continue
+        },
+        |_| Walk::Continue
+    );
+    assert_eq!(values(&tree).join(" "), "ROOT A A1 A2 C");
+    assert!(tree.rollback());
+    assert_eq!(values(&tree).join(" "), "root a a1 a2 c");
+
+    tree.set_history_limit(1);
+    for value in &["y", "z"] {
+        tree.begin();
+        tree.get_mut(a_id).set_value(value.to_string());
+        tree.commit();
+    }
+    assert!(tree.undo());
+    assert!(!tree.undo());
+    assert_eq!(tree.get(a_id).try_value().unwrap(), "y");
+}
+
+#[test]
+#[cfg(feature = "persistent-arena")]
+fn test_snapshot() {
+    let mut tree = build_tree();
+    let root_id = tree.root();
+    let a_id = tree.get(root_id).first_child_id().unwrap();
+    let c_id = tree.get(root_id).last_child_id().unwrap();
+
+    let snapshot = tree.snapshot();
+    tree.get_mut(a_id).set_value("A".to_string());
+    tree.get_mut(c_id).move_to(a_id, 0);
+    tree.split_off(a_id);
+    assert_eq!(values(&tree), vec!["root", "b", "b1"]);
+    assert_eq!(values(&snapshot), vec!["root", "a", "a1", "a2", "b", "b1", "c"]);
+
+    let mut later = snapshot.snapshot();
+    for i in 0..2000 {
+        later.get_mut(c_id).append(i.to_string());
+    }
+    assert_eq!(later.get(c_id).children_values_iter().count(), 2000);
+
+    let last_id = later.get(c_id).last_child_id().unwrap();
+    let latest = later.snapshot();
+    later.get_mut(last_id).set_value("last".to_string());
+    assert_eq!(later.get(last_id).try_value().unwrap(), "last");
+    assert_eq!(latest.get(last_id).try_value().unwrap(), "1999");
+    assert_eq!(snapshot.get(c_id).first_child_id(), None);
+    assert_eq!(snapshot.get(a_id).try_value().unwrap(), "a");
+    assert_eq!(values(&tree), vec!["root", "b", "b1"]);
+
+    // Snapshots are compared by their values, and other trees can be appended to them.
+    assert_eq!(latest.snapshot(), latest);
+    assert_ne!(later, latest);
+    assert!(later.get_mut(c_id).append_tree(build_tree()));
+    assert_eq!(later.get(c_id).children_values_iter().count(), 2003);
+    assert_eq!(
+        later.get(c_id).last_child().unwrap().descendants_values_iter().collect::<Vec<_>>(),
+        vec!["c"]
+    );
+}
+
+#[test]
+fn test_node_path() {
+    let mut tree = build_tree();
+    let root_id = tree.root();
+    let b_id = tree.get(root_id).children_ids_iter().nth(1).unwrap();
+    let b1_id = tree.get(b_id).first_child_id().unwrap();
+
+    let path = tree.get(b1_id).path().unwrap();
+    assert_eq!(path, NodePath::from(vec![1, 0]));
+    assert_eq!(path.to_string(), "/1/0");
+    assert_eq!(path.parent(), tree.get(b_id).path());
+    assert_eq!(tree.get(root_id).path(), Some(NodePath::root()));
+    assert_eq!(NodePath::root().to_string(), "/");
+
+    assert_eq!("/1/0".parse::<NodePath>(), Ok(path.clone()));
+    assert_eq!("/".parse::<NodePath>(), Ok(NodePath::root()));
+    assert!("/1/b".parse::<NodePath>().is_err());
+    assert_eq!("1/0".parse::<NodePath>(), Err(ParseNodePathError::MissingRoot));
+    assert_eq!("////1".parse::<NodePath>(), Err(ParseNodePathError::EmptySegment));
+    assert_eq!("/1/".parse::<NodePath>(), Err(ParseNodePathError::EmptySegment));
+    assert_eq!("".parse::<NodePath>(), Err(ParseNodePathError::MissingRoot));
+
+    assert_eq!(tree.resolve(&path), Some(b1_id));
+    assert_eq!(tree.resolve(&NodePath::root()), Some(root_id));
+    assert_eq!(tree.resolve(&path.child(0)), None);
+
+    tree.get_mut(b_id).detach();
+    assert_eq!(tree.get(b1_id).path(), None);
+}
+
+#[derive(Debug, PartialEq, Clone)]
+struct Joined(String);
+
+impl Aggregate<String> for Joined {
+    fn from_value(value: &String) -> Self {
+        Joined(value.clone())
+    }
+
+    fn combine(&self, other: &Self) -> Self {
+        Joined(format!("{} {}", self.0, other.0))
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+struct Count(usize);
+
+impl Aggregate<String> for Count {
+    fn from_value(_: &String) -> Self {
+        Count(1)
+    }
+
+    fn combine(&self, other: &Self) -> Self {
+        Count(self.0 + other.0)
+    }
+}
+
+#[test]
+fn test_fold_and_aggregate() {
+    let mut tree = build_tree();
+    let root_id = tree.root();
+    let a_id = tree.get(root_id).first_child_id().unwrap();
+    let a1_id = tree.get(a_id).first_child_id().unwrap();
+    let b_id = tree.get(a_id).next_sibling_id().unwrap();
+    let c_id = tree.get(root_id).last_child_id().unwrap();
+
+    let post_order = tree.get(root_id).fold_subtree(vec![], |mut acc, node| {
+        acc.push(node.try_value().unwrap().clone());
+        acc
+    });
+    assert_eq!(post_order, vec!["a1", "a2", "a", "b1", "b", "c", "root"]);
+    assert_eq!(tree.get(a_id).fold_subtree(0, |acc, _| acc + 1), 3);
+
+    let joined = |tree: &Tree<String>, id| tree.get(id).aggregate::<Joined>().unwrap().0;
+    assert_eq!(tree.get(root_id).aggregate::<Joined>(), None);
+
+    tree.enable_aggregates::<Joined>();
+    assert_eq!(joined(&tree, a_id), "a a1 a2");
+    assert_eq!(joined(&tree, root_id), "root a a1 a2 b b1 c");
+    assert_eq!(tree.get(root_id).aggregate::<Count>(), None);
+
+    tree.get_mut(a1_id).try_value_mut().unwrap().push('!');
+    tree.get_mut(c_id).append("d".to_string());
+    tree.get_mut(b_id).detach();
+    assert_eq!(joined(&tree, root_id), "root a a1! a2 c d");
+    assert_eq!(joined(&tree, b_id), "b b1");
+
+    tree.get_mut(root_id).reverse_children();
+    tree.get_mut(root_id).walk_mut(
+        |mut node| {
+            node.try_value_mut().unwrap().make_ascii_uppercase();
+            Walk::Continue
+        },
+        |_| Walk::Continue
+    );
+    assert_eq!(joined(&tree, root_id), "ROOT C D A A1! A2");
+
+    tree.begin();
+    tree.get_mut(a_id).move_to(c_id, 0);
+    tree.commit();
+    assert_eq!(joined(&tree, root_id), "ROOT C A A1! A2 D");
+    tree.undo();
+    assert_eq!(joined(&tree, root_id), "ROOT C D A A1! A2");
+
+    tree.enable_aggregates::<Count>();
+    assert_eq!(tree.get(root_id).aggregate::<Count>(), Some(Count(6)));
+    assert_eq!(tree.get(root_id).aggregate::<Joined>(), None);
+}
+
+#[test]
+fn test_subtree_hash() {
+    let mut tree = build_tree();
+    let other = build_tree();
+    let root_id = tree.root();
+    let a_id = tree.get(root_id).first_child_id().unwrap();
+    let b_id = tree.get(a_id).next_sibling_id().unwrap();
+    let c_id = tree.get(root_id).last_child_id().unwrap();
+
+    let hash = tree.get(root_id).subtree_hash();
+    assert_eq!(hash, other.get(other.root()).subtree_hash());
+    let clone = tree.clone_subtree(a_id);
+    assert_eq!(tree.get(a_id).subtree_hash(), clone.get(clone.root()).subtree_hash());
+    assert_ne!(tree.get(a_id).subtree_hash(), tree.get(b_id).subtree_hash());
+
+    tree.enable_subtree_hashes();
+    assert_eq!(tree.get(root_id).subtree_hash(), hash);
+
+    tree.get_mut(c_id).append("a1".to_string());
+    assert_ne!(tree.get(root_id).subtree_hash(), hash);
+    let clone = tree.clone_subtree(root_id);
+    assert_eq!(tree.get(root_id).subtree_hash(), clone.get(clone.root()).subtree_hash());
+
+    let c1_id = tree.get(c_id).first_child_id().unwrap();
+    tree.get_mut(c1_id).detach();
+    assert_eq!(tree.get(root_id).subtree_hash(), hash);
+}
+
+#[test]
+#[cfg(feature = "rayon")]
+fn test_parallel() {
+    let mut tree = build_tree();
+    let root_id = tree.root();
+    let a_id = tree.get(root_id).first_child_id().unwrap();
+
+    let mut found: Vec<&str> = tree.get(root_id).par_descendants().map(|(_, v)| v.as_str()).collect();
+    found.sort();
+    assert_eq!(found, vec!["a", "a1", "a2", "b", "b1", "c", "root"]);
+
+    tree.get_mut(a_id).par_for_each_mut(|v| v.push('!')).unwrap();
+    assert_eq!(values(&tree), vec!["root", "a!", "a1!", "a2!", "b", "b1", "c"]);
+
+    let records = Rc::new(RefCell::new(0));
+    let counter = Rc::clone(&records);
+    tree.observe(move |_, _| *counter.borrow_mut() += 1);
+
+    let joined = tree.get_mut(root_id)
+        .par_map_subtrees(|v: &mut String, children: Vec<String>| {
+            v.make_ascii_uppercase();
+            match children.is_empty() {
+                true => v.clone(),
+                false => format!("{}({})", v, children.join(","))
+            }
+        })
+        .unwrap();
+    assert_eq!(joined, "ROOT(A!(A1!,A2!),B(B1),C)");
+    assert_eq!(values(&tree), vec!["ROOT", "A!", "A1!", "A2!", "B", "B1", "C"]);
+    assert_eq!(*records.borrow(), 7);
+}
+
+#[test]
+fn test_forest() {
+    let mut forest = Forest::new();
+    assert!(forest.is_empty());
+
+    let b_id = forest.push_root("b".to_string());
+    let a_id = forest.insert_root(0, "a".to_string());
+    forest.get_mut(a_id).append("a1".to_string());
+    let tree_id = forest.push_tree(build_tree());
+    assert_eq!(forest.len(), 3);
+    assert!(forest.is_root(tree_id));
+    assert_eq!(forest.roots_values_iter().map(|v| v.as_str()).collect::<Vec<_>>(), vec!["a", "b", "root"]);
+    assert_eq!(forest.get(tree_id).descendants_values_iter().count(), 7);
+
+    let a = forest.remove_root(a_id).unwrap();
+    assert_eq!(values(&a), vec!["a", "a1"]);
+    assert_eq!(forest.roots_ids_iter().collect::<Vec<_>>(), vec![b_id, tree_id]);
+
+    let tree = forest.into_tree("top".to_string());
+    assert_eq!(values(&tree), vec!["top", "b", "root", "a", "a1", "a2", "b", "b1", "c"]);
+
+    let (value, forest) = build_tree().into_forest();
+    assert_eq!(value, "root");
+    let trees = forest.into_trees();
+    assert_eq!(trees.iter().map(|tree| values(tree)).collect::<Vec<_>>(), vec![vec!["a", "a1", "a2"], vec!["b", "b1"], vec!["c"]]);
+}
+
+#[test]
+fn test_builders() {
+    let mut builder = TreeBuilder::new();
+    builder.open("root").open("a").leaf("a1").leaf("a2").close();
+    builder.open("b").leaf("b1").close().leaf("c");
+    let tree: Tree<String> = builder.build().unwrap();
+    assert_eq!(values(&tree), values(&build_tree()));
+    assert_eq!(tree.get(tree.root()).last_child().unwrap().prev_sibling().unwrap().try_value().unwrap(), "b");
+
+    let mut builder = TreeBuilder::<String>::new();
+    builder.open("a").close().leaf("b");
+    assert!(builder.build().is_none());
+
+    let tree: Tree<String> = Tree::from_nested((
+        "root".to_string(),
+        vec![
+            ("a".to_string(), vec![Nested("a1".to_string(), vec![]), Nested("a2".to_string(), vec![])]).into(),
+            ("b".to_string(), vec![Nested("b1".to_string(), vec![])]).into(),
+            Nested("c".to_string(), vec![]),
+        ]
+    ));
+    assert_eq!(values(&tree), values(&build_tree()));
+
+    let preorder = vec![(0, "root"), (1, "a"), (2, "a1"), (2, "a2"), (1, "b"), (2, "b1"), (1, "c")];
+    let mut tree = Tree::from_preorder(preorder.into_iter().map(|(depth, v)| (depth, v.to_string()))).unwrap();
+    assert_eq!(values(&tree), values(&build_tree()));
+    assert!(Tree::<String>::from_preorder(vec![(0, "a".to_string()), (0, "b".to_string())]).is_none());
+    assert!(Tree::<String>::from_preorder(vec![(0, "a".to_string()), (2, "b".to_string())]).is_none());
+    assert!(Tree::<String>::from_preorder(vec![(0, "a".to_string()), (1, "b".to_string()), (3, "c".to_string())]).is_none());
+
+    let root_id = tree.root();
+    tree.get_mut(root_id).append_preorder(vec![(0, "d".to_string()), (1, "d1".to_string())]);
+    assert_eq!(values(&tree), vec!["root", "a", "a1", "a2", "b", "b1", "c", "d", "d1"]);
+}
+
+#[test]
+fn test_filter_descendants() {
+    let mut tree = build_tree();
+    let root_id = tree.root();
+    let a_id = tree.get(root_id).first_child_id().unwrap();
+
+    let removed = tree.get_mut(root_id).retain_descendants(|node| !node.try_value().unwrap().ends_with('1'));
+    assert_eq!(removed, 2);
+    assert_eq!(values(&tree), vec!["root", "a", "a2", "b", "c"]);
+
+    let removed = tree.get_mut(a_id).retain_descendants(|_| false);
+    assert_eq!(removed, 1);
+    assert_eq!(values(&tree), vec!["root", "a", "b", "c"]);
+
+    let mut tree = build_tree();
+    let root_id = tree.root();
+    let removed = tree.get_mut(root_id).filter_descendants(|node| match node.try_value().unwrap().as_str() {
+        "a" | "b" => FilterAction::Unwrap,
+        "a2" | "c" => FilterAction::Remove,
+        _ => FilterAction::Keep
+    });
+    assert_eq!(removed, 4);
+    assert_eq!(values(&tree), vec!["root", "a1", "b1"]);
+}
+
+#[test]
+fn test_collect_garbage() {
+    let mut tree = build_tree();
+    let root_id = tree.root();
+    let a_id = tree.get(root_id).first_child_id().unwrap();
+    let b_id = tree.get(a_id).next_sibling_id().unwrap();
+    assert!(tree.orphans().is_empty());
+
+    let lone_id = tree.alloc("lone".to_string());
+    tree.get_mut(a_id).detach();
+    tree.get_mut(b_id).detach();
+    tree.pin(b_id);
+    assert_eq!(tree.orphans().len(), 4);
+    assert!(!tree.orphans().contains(&b_id));
+
+    assert_eq!(tree.collect_garbage(), 4);
+    assert!(tree.orphans().is_empty());
+    assert!(tree.get(lone_id).try_value().is_none());
+    assert_eq!(tree.get(b_id).descendants_values_iter().collect::<Vec<_>>(), vec!["b", "b1"]);
+
+    assert!(tree.unpin(b_id));
+    assert_eq!(tree.collect_garbage(), 2);
+    assert_eq!(values(&tree), vec!["root", "c"]);
+
+    let mut forest = Forest::new();
+    forest.push_tree(build_tree());
+    let d_id = forest.push_root("d".to_string());
+    assert_eq!(forest.get_mut(d_id).tree_mut().collect_garbage(), 0);
+}
+
+#[test]
+fn test_range_operations() {
+    let mut tree = build_tree();
+    let root_id = tree.root();
+    let a_id = tree.get(root_id).first_child_id().unwrap();
+    let b_id = tree.get(a_id).next_sibling_id().unwrap();
+    let c_id = tree.get(root_id).last_child_id().unwrap();
+    let b1_id = tree.get(b_id).first_child_id().unwrap();
+
+    assert_eq!(tree.get_mut(root_id).children_range(a_id, b_id), Some(vec![a_id, b_id]));
+    assert_eq!(tree.get_mut(root_id).children_range(b_id, a_id), None);
+    assert_eq!(tree.get_mut(a_id).children_range(a_id, b_id), None);
+
+    let records = Rc::new(RefCell::new(vec![]));
+    let log = Rc::clone(&records);
+    tree.observe(move |record, _| log.borrow_mut().push(*record));
+
+    tree.get_mut(root_id).move_range_after(a_id, b_id, c_id).unwrap();
+    assert_eq!(values(&tree), vec!["root", "c", "a", "a1", "a2", "b", "b1"]);
+    assert_eq!(records.borrow().len(), 4);
+    assert_eq!(tree.get_mut(root_id).move_range_after(a_id, b_id, b1_id), None);
+
+    tree.get_mut(root_id).move_range_after(c_id, a_id, b1_id).unwrap();
+    assert_eq!(values(&tree), vec!["root", "b", "b1", "c", "a", "a1", "a2"]);
+    assert_eq!(tree.get(a_id).parent_id(), Some(b_id));
+    assert_eq!(tree.get(root_id).last_child_id(), Some(b_id));
+
+    assert_eq!(tree.get_mut(b_id).detach_range(b1_id, c_id), Some(vec![b1_id, c_id]));
+    assert_eq!(values(&tree), vec!["root", "b", "a", "a1", "a2"]);
+    assert_eq!(tree.get(c_id).parent_id(), None);
+    assert_eq!(tree.get(c_id).prev_sibling_id(), None);
+    assert_eq!(
+        records.borrow().last(),
+        Some(&MutationRecord::Detach {
+            parent_id: b_id,
+            child_id: c_id,
+            prev_sibling_id: None,
+            next_sibling_id: Some(a_id)
+        })
+    );
+}